@@ -1,36 +1,79 @@
-use crate::schedule::{ScdDuration, ScheduleError, ScheduleLine, SchedulingMode};
-use chrono::{DateTime, NaiveDate, Utc};
+use crate::archive;
+use crate::backup::{self, Backup};
+use crate::experiment::{load_experiments, Experiment};
+use crate::fuzzy::fuzzy_match;
+use crate::lock::{LockError, ScheduleLock};
+use crate::schedule::conflict::{check_line, ScheduleWarning};
+use crate::schedule::diff::{diff, DiffLine};
+use crate::schedule::document::{LineDiagnostic, MergeConflict, ScdDocument};
+use crate::schedule::query::{Query, QueryError};
+use crate::schedule::recurrence::Recurrence;
+use crate::schedule::timezone::{self, Zone};
+use crate::schedule::when;
+use crate::schedule::{
+    Field, FieldError, Kwargs, ScdDuration, ScheduleError, ScheduleLine, SchedulingMode,
+};
+use chrono::{DateTime, Duration, DurationRound, NaiveTime, Utc};
 use ratatui::widgets::ListState;
+use std::cmp::Reverse;
 use std::error::Error;
-use std::fs::File;
-use std::io;
-use std::io::{BufRead, Write};
+use std::ffi::OsString;
+use std::fs::{File, Metadata};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Number of days shown in the timeline view when it is first opened.
+const DEFAULT_TIMELINE_DAYS: i64 = 7;
+/// Longest window the timeline view can be zoomed out to.
+const MAX_TIMELINE_DAYS: i64 = 90;
 
 /// Different screens that the application can be on.
 pub enum CurrentScreen {
     Main,
     Adding,
+    Editing,
     Removing,
     Selecting,
+    Timeline,
+    Diagnostics,
     Exiting,
+    Merging,
+    Backups,
+    Searching,
+    Archiving,
 }
 
 /// Different fields that the user may edit.
 #[derive(Debug, Copy, Clone)]
 pub enum CurrentlyEditing {
-    Year,
-    Month,
-    Day,
-    Hour,
-    Minute,
+    Start,
+    Timezone,
     Duration,
+    End,
     Priority,
     Experiment,
     SchedulingMode,
     Kwargs,
+    Recurrence,
     Done,
 }
+impl From<Field> for CurrentlyEditing {
+    fn from(field: Field) -> Self {
+        match field {
+            Field::Start => Self::Start,
+            Field::Timezone => Self::Timezone,
+            Field::Duration => Self::Duration,
+            Field::End => Self::End,
+            Field::Priority => Self::Priority,
+            Field::Experiment => Self::Experiment,
+            Field::SchedulingMode => Self::SchedulingMode,
+            Field::Kwargs => Self::Kwargs,
+            Field::Recurrence => Self::Recurrence,
+        }
+    }
+}
 
 pub(crate) struct InternalList<T> {
     pub(crate) items: Vec<T>,
@@ -39,7 +82,7 @@ pub(crate) struct InternalList<T> {
 
 impl<T> InternalList<T> {
     pub fn next(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect()
         } else {
             let i = match self.state.selected() {
@@ -57,7 +100,7 @@ impl<T> InternalList<T> {
     }
 
     pub fn previous(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect()
         } else {
             let i = match self.state.selected() {
@@ -75,7 +118,7 @@ impl<T> InternalList<T> {
     }
 
     pub fn first(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect()
         } else {
             self.state.select(Some(0));
@@ -83,7 +126,7 @@ impl<T> InternalList<T> {
     }
 
     pub fn last(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect()
         } else {
             self.state.select(Some(self.items.len() - 1));
@@ -97,45 +140,189 @@ impl<T> InternalList<T> {
     }
 }
 
+/// Modification time, size and contents hash of a schedule file, for noticing when someone else
+/// changes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+impl FileStamp {
+    fn new(text: &str, metadata: &Metadata) -> FileStamp {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Whether the file at `path` still has the modification time and size it had when `self`
+    /// was taken, in which case it is taken to be unchanged without reading it, as `rsync` does.
+    fn looks_unchanged(&self, path: &Path) -> bool {
+        match std::fs::metadata(path) {
+            Ok(x) => {
+                self.modified.is_some() && x.modified().ok() == self.modified && x.len() == self.len
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Whether the contents of the file differ from when `self` was taken.
+    pub fn changed(&self, other: &FileStamp) -> bool {
+        self.hash != other.hash
+    }
+}
+
+/// Why a schedule could not be opened for editing.
+#[derive(thiserror::Error, Debug)]
+pub enum OpenError {
+    #[error(transparent)]
+    Lock(#[from] LockError),
+
+    #[error("Unable to open schedule file: {0}")]
+    Schedule(Box<dyn Error>),
+
+    #[error("Unable to find Borealis experiments: {0}")]
+    Experiments(Box<dyn Error>),
+}
+
+/// Our changes merged onto a version of the schedule file that someone else saved after we
+/// loaded it.
+pub struct Merge {
+    pub document: ScdDocument,
+    pub conflicts: Vec<MergeConflict>,
+    pub stamp: FileStamp,
+}
+
+/// The parts of the application state that are changed by editing the schedule.
+#[derive(Clone)]
+struct EditState {
+    schedule: Vec<ScheduleLine>,
+    document: ScdDocument,
+    removed_invalid: Vec<String>,
+    additions: Vec<ScheduleLine>,
+    deletions: Vec<ScheduleLine>,
+    modifications: Vec<(ScheduleLine, ScheduleLine)>,
+    archived: Vec<ScheduleLine>,
+}
+
+/// A change to a single schedule line, which can be made again on the schedule of another site.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Add(ScheduleLine),
+    Remove(ScheduleLine),
+    Edit(ScheduleLine, ScheduleLine),
+}
+
+/// An edit of the schedule, holding the state from either side of it so that it can be undone
+/// and redone.
+pub struct Operation {
+    pub description: String,
+    before: EditState,
+    after: EditState,
+}
+
 /// State of the application.
 pub struct App {
-    pub year_input: String,
-    pub month_input: String,
-    pub day_input: String,
-    pub hour_input: String,
-    pub minute_input: String,
+    /// Start of the line, in any of the forms of [`crate::schedule::when`].
+    pub start_input: String,
+    /// Time zone the start and end inputs are in, left empty for UTC. It is kept between lines.
+    pub zone_input: String,
     pub duration_input: String,
+    /// End of the line, which can be given instead of its duration.
+    pub end_input: String,
     pub priority_input: String,
-    pub experiment_list: InternalList<String>,
+    /// Every known experiment, of which `experiment_list` holds those matching the filter.
+    pub all_experiments: Vec<Experiment>,
+    pub experiment_list: InternalList<Experiment>,
+    pub experiment_filter: String,
+    /// The experiment that was selected before filtering started.
+    pub experiment_before_filter: Option<String>,
     pub mode_list: InternalList<SchedulingMode>,
     pub kwarg_input: String,
+    /// Rule for repeating a new line, left empty to add it once.
+    pub recurrence_input: String,
+    /// Lines the recurrence rule makes, with their warnings, shown before they are added.
+    pub recurrence_preview: Vec<(ScheduleLine, Vec<ScheduleWarning>)>,
     pub schedule_list: InternalList<ScheduleLine>,
+    /// Query being typed after pressing `/`, and why it could not be used, if it couldn't.
+    pub search_input: String,
+    pub search_error: Option<QueryError>,
+    /// Only lines matching the search are shown and can be selected.
+    pub search: Option<Query>,
+    /// Scroll state of the list of matching lines.
+    pub search_state: ListState,
     pub current_screen: CurrentScreen,
     pub currently_editing: Option<CurrentlyEditing>,
     pub last_err: Option<ScheduleError>,
+    pub pending_warnings: Vec<ScheduleWarning>,
+    /// The line that `pending_warnings` are for, which must be the one added once they are
+    /// confirmed.
+    pub pending_line: Option<ScheduleLine>,
     pub scd_path: PathBuf,
+    pub lock: ScheduleLock,
+    /// The schedule as it was loaded, and a stamp of the file it was loaded from.
+    pub base: ScdDocument,
+    pub base_stamp: FileStamp,
+    pub merge: Option<Merge>,
+    pub keep_backups: usize,
+    pub backups: InternalList<Backup>,
+    /// Lines that restoring the selected backup would change, or why it could not be read,
+    /// worked out when the selection changes rather than every time the screen is drawn.
+    pub backup_changes: Result<Vec<DiffLine>, String>,
+    /// How far the changes of the selected backup are scrolled down.
+    pub backup_scroll: u16,
+    pub document: ScdDocument,
+    pub diagnostics: InternalList<LineDiagnostic>,
+    pub fix_input: Option<String>,
+    pub removed_invalid: Vec<String>,
     pub additions: Vec<ScheduleLine>,
     pub deletions: Vec<ScheduleLine>,
+    pub modifications: Vec<(ScheduleLine, ScheduleLine)>,
+    /// Lines removed by archiving, which are written to the archive file when saving.
+    pub archived: Vec<ScheduleLine>,
+    /// Lines that archiving would remove, shown for confirmation.
+    pub archive_preview: Vec<ScheduleLine>,
+    pub editing_line: Option<ScheduleLine>,
+    pub undo_stack: Vec<Operation>,
+    pub redo_stack: Vec<Operation>,
+    /// The last edit, if it changed a single line, for copying to other sites.
+    pub last_change: Option<Change>,
+    pub timeline_start: DateTime<Utc>,
+    pub timeline_days: i64,
 }
 
 impl App {
-    pub fn new(scd_path: PathBuf, exp_path: PathBuf) -> App {
-        let current_schedule =
-            Self::load_schedule(&scd_path).expect("Unable to open schedule file");
-        let available_experiments =
-            load_experiments(&exp_path).expect("Unable to find Borealis experiments");
+    /// Opens the schedule at `scd_path` for editing, locking it so that nobody else can edit it
+    /// at the same time. If `force_lock` is set, any existing lock is taken over. Each save keeps
+    /// up to `keep_backups` backups of the schedule.
+    pub fn new(
+        scd_path: PathBuf,
+        exp_path: PathBuf,
+        force_lock: bool,
+        keep_backups: usize,
+    ) -> Result<App, OpenError> {
+        let lock = ScheduleLock::acquire(&scd_path, force_lock)?;
+        let (document, base_stamp) = Self::read_schedule(&scd_path).map_err(OpenError::Schedule)?;
+        let mut current_schedule = document.lines();
+        current_schedule.reverse();
+        let available_experiments = load_experiments(&exp_path).map_err(OpenError::Experiments)?;
         let mut app = App {
-            year_input: String::new(),
-            month_input: String::new(),
-            day_input: String::new(),
-            hour_input: String::new(),
-            minute_input: String::new(),
+            start_input: String::new(),
+            zone_input: String::new(),
             duration_input: String::new(),
+            end_input: String::new(),
             priority_input: String::new(),
             experiment_list: InternalList {
-                items: available_experiments,
+                items: available_experiments.clone(),
                 state: ListState::default(),
             },
+            all_experiments: available_experiments,
+            experiment_filter: String::new(),
+            experiment_before_filter: None,
             mode_list: InternalList {
                 items: vec![
                     SchedulingMode::Common,
@@ -145,47 +332,80 @@ impl App {
                 state: ListState::default(),
             },
             kwarg_input: String::new(),
+            recurrence_input: String::new(),
+            recurrence_preview: vec![],
             schedule_list: InternalList {
                 items: vec![],
                 state: ListState::default(),
             },
+            search_input: String::new(),
+            search_error: None,
+            search: None,
+            search_state: ListState::default(),
             current_screen: CurrentScreen::Main,
             currently_editing: None,
             last_err: None,
+            pending_warnings: vec![],
+            pending_line: None,
             scd_path,
+            lock,
+            base: document.clone(),
+            base_stamp,
+            merge: None,
+            keep_backups,
+            backups: InternalList {
+                items: vec![],
+                state: ListState::default(),
+            },
+            backup_changes: Ok(vec![]),
+            backup_scroll: 0,
+            diagnostics: InternalList {
+                items: document.diagnostics(),
+                state: ListState::default(),
+            },
+            fix_input: None,
+            removed_invalid: vec![],
+            document,
             additions: vec![],
             deletions: vec![],
+            modifications: vec![],
+            archived: vec![],
+            archive_preview: vec![],
+            editing_line: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            last_change: None,
+            timeline_start: Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc(),
+            timeline_days: DEFAULT_TIMELINE_DAYS,
         };
         app.mode_list.first();
         app.schedule_list.items = current_schedule;
-        app
+        if !app.diagnostics.items.is_empty() {
+            app.current_screen = CurrentScreen::Diagnostics;
+            app.diagnostics.first();
+        }
+        Ok(app)
     }
 
     /// Moves to the previous field in the editor.
     pub fn backward_toggle(&mut self) {
         if let Some(editing) = &self.currently_editing {
             match editing {
-                CurrentlyEditing::Year => {
+                CurrentlyEditing::Start => {
                     self.currently_editing = Some(CurrentlyEditing::Done);
                 }
-                CurrentlyEditing::Month => {
-                    self.currently_editing = Some(CurrentlyEditing::Year);
-                }
-                CurrentlyEditing::Day => {
-                    self.currently_editing = Some(CurrentlyEditing::Month);
-                }
-                CurrentlyEditing::Hour => {
-                    self.currently_editing = Some(CurrentlyEditing::Day);
-                }
-                CurrentlyEditing::Minute => {
-                    self.currently_editing = Some(CurrentlyEditing::Hour);
+                CurrentlyEditing::Timezone => {
+                    self.currently_editing = Some(CurrentlyEditing::Start);
                 }
                 CurrentlyEditing::Duration => {
-                    self.currently_editing = Some(CurrentlyEditing::Minute);
+                    self.currently_editing = Some(CurrentlyEditing::Timezone);
                 }
-                CurrentlyEditing::Priority => {
+                CurrentlyEditing::End => {
                     self.currently_editing = Some(CurrentlyEditing::Duration);
                 }
+                CurrentlyEditing::Priority => {
+                    self.currently_editing = Some(CurrentlyEditing::End);
+                }
                 CurrentlyEditing::Experiment => {
                     self.currently_editing = Some(CurrentlyEditing::Priority);
                 }
@@ -195,9 +415,12 @@ impl App {
                 CurrentlyEditing::Kwargs => {
                     self.currently_editing = Some(CurrentlyEditing::SchedulingMode);
                 }
-                CurrentlyEditing::Done => {
+                CurrentlyEditing::Recurrence => {
                     self.currently_editing = Some(CurrentlyEditing::Kwargs);
                 }
+                CurrentlyEditing::Done => {
+                    self.currently_editing = Some(CurrentlyEditing::Recurrence);
+                }
             }
         }
     }
@@ -205,22 +428,16 @@ impl App {
     pub fn forward_toggle(&mut self) {
         if let Some(editing) = &self.currently_editing {
             match editing {
-                CurrentlyEditing::Year => {
-                    self.currently_editing = Some(CurrentlyEditing::Month);
-                }
-                CurrentlyEditing::Month => {
-                    self.currently_editing = Some(CurrentlyEditing::Day);
-                }
-                CurrentlyEditing::Day => {
-                    self.currently_editing = Some(CurrentlyEditing::Hour);
+                CurrentlyEditing::Start => {
+                    self.currently_editing = Some(CurrentlyEditing::Timezone);
                 }
-                CurrentlyEditing::Hour => {
-                    self.currently_editing = Some(CurrentlyEditing::Minute);
-                }
-                CurrentlyEditing::Minute => {
+                CurrentlyEditing::Timezone => {
                     self.currently_editing = Some(CurrentlyEditing::Duration);
                 }
                 CurrentlyEditing::Duration => {
+                    self.currently_editing = Some(CurrentlyEditing::End);
+                }
+                CurrentlyEditing::End => {
                     self.currently_editing = Some(CurrentlyEditing::Priority);
                 }
                 CurrentlyEditing::Priority => {
@@ -233,84 +450,83 @@ impl App {
                     self.currently_editing = Some(CurrentlyEditing::Kwargs);
                 }
                 CurrentlyEditing::Kwargs => {
+                    self.currently_editing = Some(CurrentlyEditing::Recurrence);
+                }
+                CurrentlyEditing::Recurrence => {
                     self.currently_editing = Some(CurrentlyEditing::Done);
                 }
                 CurrentlyEditing::Done => {
-                    self.currently_editing = Some(CurrentlyEditing::Year);
+                    self.currently_editing = Some(CurrentlyEditing::Start);
                 }
             }
         }
     }
 
-    /// Attempts to create a schedule line from the internal buffers holding the user input
-    fn create_line_from_inputs(&mut self) -> Result<ScheduleLine, ScheduleError> {
-        let year: u16 = self
-            .year_input
-            .parse()
-            .map_err(|_| ScheduleError::InvalidDate(format!("Bad year: {}", self.year_input)))?;
-        if year < 2000 || year > 2050 {
-            return Err(ScheduleError::InvalidDate(format!(
-                "Bad year: {year} not in range [2000, 2050]"
-            )));
-        }
-        let month: u8 = self
-            .month_input
-            .parse()
-            .map_err(|_| ScheduleError::InvalidDate(format!("Bad month: {}", self.month_input)))?;
-        if month == 0 || month > 12 {
-            return Err(ScheduleError::InvalidDate(format!(
-                "Bad month: {month} not in range [1, 12]"
-            )));
-        }
-        let day: u8 = self
-            .day_input
-            .parse()
-            .map_err(|_| ScheduleError::InvalidDate(format!("Bad day: {}", self.day_input)))?;
-        if day == 0 || day > 31 {
-            return Err(ScheduleError::InvalidDate(format!(
-                "Bad day: {day} not in range [1, 31]"
-            )));
-        }
-        let hour: u8 = self
-            .hour_input
-            .parse()
-            .map_err(|_| ScheduleError::InvalidTime(format!("Bad hour: {}", self.hour_input)))?;
-        if hour > 23 {
-            return Err(ScheduleError::InvalidTime(format!(
-                "Bad hour: {hour} not in range [0, 23]"
-            )));
-        }
-        let minute: u8 = self.minute_input.parse().map_err(|_| {
-            ScheduleError::InvalidTime(format!("Bad minute: {}", self.minute_input))
+    /// The start time given by the start input, converted from the zone it is in. Times such as
+    /// `now` are rounded up to the next whole minute, as schedule lines have no seconds.
+    fn start_from_inputs(&self) -> Result<DateTime<Utc>, ScheduleError> {
+        let zone = Zone::parse(&self.zone_input, &self.site())?;
+        let start = when::parse_start(&self.start_input, zone, Utc::now())?;
+        Ok(start
+            .duration_round_up(Duration::minutes(1))
+            .unwrap_or(start))
+    }
+
+    /// The duration given by the duration input, or by the end input for a line starting at
+    /// `start`. Only one of them may be filled in.
+    fn duration_from_inputs(&self, start: DateTime<Utc>) -> Result<ScdDuration, ScheduleError> {
+        if self.end_input.trim().is_empty() {
+            return ScdDuration::try_from(&self.duration_input);
+        }
+        let invalid = |expected: &str| {
+            ScheduleError::InvalidDuration(FieldError::new(Field::End, &self.end_input, expected))
+        };
+        if !self.duration_input.trim().is_empty() {
+            return Err(invalid("an end or a duration, not both"));
+        }
+        let zone = Zone::parse(&self.zone_input, &self.site())?;
+        let end = when::parse_end(&self.end_input, zone, start, Utc::now())?;
+        let end = end.duration_round_up(Duration::minutes(1)).unwrap_or(end);
+        if end <= start {
+            return Err(invalid("an end after the start"));
+        }
+        Ok(ScdDuration::Finite(end - start))
+    }
+
+    /// The start time being entered, if the inputs give a valid one, for showing in UTC and local
+    /// time beside the inputs.
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.start_from_inputs().ok()
+    }
+
+    /// The local time zone of the site, or the zone times are being entered in if the site's
+    /// zone isn't known.
+    pub fn local_zone(&self) -> Option<Zone> {
+        match timezone::site(&self.site()) {
+            Some(site) => Some(Zone::Named(site.timezone)),
+            None => Zone::parse(&self.zone_input, &self.site())
+                .ok()
+                .filter(|x| *x != Zone::Utc),
+        }
+    }
+
+    /// Attempts to create a schedule line from the internal buffers holding the user input, along
+    /// with warnings about kwargs the experiment may not accept
+    fn create_line_from_inputs(
+        &mut self,
+    ) -> Result<(ScheduleLine, Vec<ScheduleWarning>), ScheduleError> {
+        let timestamp = self.start_from_inputs()?;
+
+        let priority: u8 = self.priority_input.parse().map_err(|_| {
+            ScheduleError::InvalidPriority(FieldError::new(
+                Field::Priority,
+                &self.priority_input,
+                "priority in range [0, 20]",
+            ))
         })?;
-        if minute > 59 {
-            return Err(ScheduleError::InvalidTime(format!(
-                "Bad minute: {minute} not in range [0, 59]"
-            )));
-        }
-
-        let timestamp: DateTime<Utc> =
-            NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32)
-                .ok_or_else(|| {
-                    ScheduleError::InvalidDate(format!(
-                        "Expected valid date as YYYYMMDD, got {year:4}{month:2}{day:2}"
-                    ))
-                })?
-                .and_hms_opt(hour as u32, minute as u32, 0)
-                .ok_or_else(|| {
-                    ScheduleError::InvalidTime(format!(
-                        "Expected valid time as HH:MM, got {hour:2}:{minute:2}"
-                    ))
-                })?
-                .and_utc();
-
-        let priority: u8 = self
-            .priority_input
-            .parse()
-            .map_err(|_| ScheduleError::InvalidPriority(self.priority_input.clone()))?;
 
         let experiment = if let Some(i) = self.experiment_list.state.selected() {
-            self.experiment_list.items[i].clone()
+            self.experiment_list.items[i].name.clone()
         } else {
             "normalscan".to_string()
         };
@@ -321,111 +537,1394 @@ impl App {
             SchedulingMode::default()
         };
 
-        ScheduleLine::new(
+        let kwargs = Kwargs::parse(&self.kwarg_input)?;
+        let kwarg_warnings = match self
+            .experiment_list
+            .items
+            .iter()
+            .find(|x| x.name == experiment)
+        {
+            Some(x) => x.check_kwargs(&kwargs)?,
+            None => vec![],
+        };
+
+        let line = ScheduleLine::new(
             timestamp,
-            ScdDuration::try_from(&self.duration_input)?,
+            self.duration_from_inputs(timestamp)?,
             priority,
             &experiment,
             &scheduling_mode,
-            self.kwarg_input.split(' ').map(|s| s.to_string()).collect(),
-        )
+            kwargs,
+        )?;
+        Ok((line, kwarg_warnings))
     }
 
-    /// Attempts to create a new schedule line from the inputs. If successful, clears the inputs
-    /// and adds the schedule line to the schedule.
-    pub fn save_entry(&mut self) -> Result<(), ScheduleError> {
+    /// Attempts to create a new schedule line from the inputs. If the line has warnings that the
+    /// user has not yet seen, they are stored in `pending_warnings` and `Ok(false)` is returned;
+    /// calling this again with the same line adds it anyway. Once the line is added, the
+    /// inputs are cleared and `Ok(true)` is returned.
+    pub fn save_entry(&mut self) -> Result<bool, ScheduleError> {
         let res = self.create_line_from_inputs();
+        if !self.recurrence_input.trim().is_empty() {
+            return self.save_recurrence(res);
+        }
         match res {
             Err(e) => {
                 self.last_err = Some(e.clone());
-                return Err(e);
+                self.pending_warnings.clear();
+                self.pending_line = None;
+                Err(e)
             }
-            Ok(new_line) => {
-                self.additions.push(new_line.clone());
+            Ok((new_line, kwarg_warnings)) => {
+                let change = match &self.editing_line {
+                    Some(original) => Change::Edit(original.clone(), new_line.clone()),
+                    None => Change::Add(new_line.clone()),
+                };
+                if let Some(e) = self.change_error(&change) {
+                    self.last_err = Some(e.clone());
+                    self.pending_warnings.clear();
+                    self.pending_line = None;
+                    return Err(e);
+                }
                 self.last_err = None;
-                self.schedule_list.items.push(new_line);
-                self.schedule_list.items.sort();
-                self.schedule_list.items.reverse();
-                self.year_input = String::new();
-                self.month_input = String::new();
-                self.day_input = String::new();
-                self.hour_input = String::new();
-                self.minute_input = String::new();
+                let mut others = self.schedule_list.items.clone();
+                if let Some(original) = &self.editing_line {
+                    if let Some(i) = others.iter().position(|x| x == original) {
+                        others.remove(i);
+                    }
+                }
+                let mut warnings = check_line(&new_line, &others, Utc::now());
+                warnings.extend(kwarg_warnings);
+                let confirmed = warnings == self.pending_warnings
+                    && self.pending_line.as_ref() == Some(&new_line);
+                if !warnings.is_empty() && !confirmed {
+                    self.pending_warnings = warnings;
+                    self.pending_line = Some(new_line);
+                    return Ok(false);
+                }
+                self.pending_warnings.clear();
+                self.pending_line = None;
+                self.editing_line = None;
+                // Checked by change_error above, so this always applies
+                self.apply_change(&change, None);
+                self.start_input = String::new();
                 self.duration_input = String::new();
+                self.end_input = String::new();
                 self.priority_input = String::new();
                 self.kwarg_input = String::new();
-                return Ok(());
+                Ok(true)
             }
         }
     }
 
-    /// Removes a line from the schedule.
+    /// Repeats `first` following the rule in `recurrence_input`. The first time this is called
+    /// with a given set of lines, they are stored in `recurrence_preview` for the user to check
+    /// and `Ok(false)` is returned; calling this again with the same lines adds them all as a
+    /// single edit.
+    fn save_recurrence(
+        &mut self,
+        first: Result<(ScheduleLine, Vec<ScheduleWarning>), ScheduleError>,
+    ) -> Result<bool, ScheduleError> {
+        let res = first.and_then(|(first, kwarg_warnings)| {
+            if self.editing_line.is_some() {
+                return Err(ScheduleError::InvalidRecurrence(FieldError::new(
+                    Field::Recurrence,
+                    &self.recurrence_input,
+                    "no recurrence when editing a line",
+                )));
+            }
+            let lines = Recurrence::parse(&self.recurrence_input)?.expand(&first)?;
+            if lines.is_empty() {
+                return Err(ScheduleError::InvalidRecurrence(FieldError::new(
+                    Field::Recurrence,
+                    &self.recurrence_input,
+                    "a rule that includes the first line",
+                )));
+            }
+            Ok((lines, kwarg_warnings))
+        });
+        let (lines, kwarg_warnings) = match res {
+            Ok(x) => x,
+            Err(e) => {
+                self.last_err = Some(e.clone());
+                self.recurrence_preview.clear();
+                return Err(e);
+            }
+        };
+        self.last_err = None;
+
+        // Each line is checked against the schedule and the repeats before it
+        let mut others = self.schedule_list.items.clone();
+        let mut preview = vec![];
+        for line in lines {
+            let mut warnings = check_line(&line, &others, Utc::now());
+            warnings.extend(kwarg_warnings.iter().cloned());
+            others.push(line.clone());
+            preview.push((line, warnings));
+        }
+        if preview != self.recurrence_preview {
+            self.recurrence_preview = preview;
+            return Ok(false);
+        }
+
+        let before = self.edit_state();
+        let count = preview.len();
+        for (line, _) in std::mem::take(&mut self.recurrence_preview) {
+            match self.deletions.iter().position(|x| *x == line) {
+                Some(i) => {
+                    self.deletions.remove(i);
+                }
+                None => self.additions.push(line.clone()),
+            }
+            self.insert_line(line);
+        }
+        self.record(
+            format!(
+                "add {count} lines repeating {}",
+                self.recurrence_input.trim()
+            ),
+            before,
+        );
+        self.start_input = String::new();
+        self.duration_input = String::new();
+        self.end_input = String::new();
+        self.priority_input = String::new();
+        self.kwarg_input = String::new();
+        self.recurrence_input = String::new();
+        Ok(true)
+    }
+
+    /// Makes `change` to the schedule as a single edit that can be undone, noting the site it
+    /// was copied `from`, if any. Returns `false` if the change can't be made because the line
+    /// to remove or edit isn't in the schedule, or the line to add or edit it into already is.
+    pub fn apply_change(&mut self, change: &Change, from: Option<&str>) -> bool {
+        if self.change_error(change).is_some() {
+            return false;
+        }
+        let before = self.edit_state();
+        let description = match change {
+            Change::Add(line) => {
+                // Adding back a removed line cancels out the removal
+                match self.deletions.iter().position(|x| x == line) {
+                    Some(i) => {
+                        self.deletions.remove(i);
+                    }
+                    None => self.additions.push(line.clone()),
+                }
+                self.insert_line(line.clone());
+                format!("add {}", line.format())
+            }
+            Change::Remove(line) => {
+                if let Some(i) = self.schedule_list.items.iter().position(|x| x == line) {
+                    self.schedule_list.items.remove(i);
+                }
+                self.record_removal(line);
+                format!("remove {}", line.format())
+            }
+            Change::Edit(original, modified) => {
+                self.record_modification(original.clone(), modified.clone());
+                self.insert_line(modified.clone());
+                format!("edit {}", original.format())
+            }
+        };
+        let description = match from {
+            Some(site) => format!("{description} (from {site})"),
+            None => description,
+        };
+        self.record(description, before);
+        self.last_change = Some(change.clone());
+        true
+    }
+
+    /// Why `change` cannot be made to the schedule as it is now, if it cannot: the line to add
+    /// is already there, or the line to remove or edit is not.
+    fn change_error(&self, change: &Change) -> Option<ScheduleError> {
+        let items = &self.schedule_list.items;
+        let invalid = |line: &ScheduleLine, expected: &str| {
+            Some(ScheduleError::InvalidChange(FieldError::new(
+                Field::Start,
+                &line.format(),
+                expected,
+            )))
+        };
+        match change {
+            Change::Add(line) if items.contains(line) => {
+                invalid(line, "a line not already in the schedule")
+            }
+            Change::Remove(line) | Change::Edit(line, _) if !items.contains(line) => {
+                invalid(line, "a line that is still in the schedule")
+            }
+            Change::Edit(original, modified)
+                if original != modified && items.contains(modified) =>
+            {
+                invalid(modified, "a line not already in the schedule")
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds `line` to the schedule list, keeping it sorted newest first.
+    fn insert_line(&mut self, line: ScheduleLine) {
+        self.schedule_list.items.push(line);
+        self.schedule_list.items.sort();
+        self.schedule_list.items.reverse();
+    }
+
+    /// Replaces `original` in the schedule with `modified`, folding the change into any earlier
+    /// addition or modification of the same line so that the change summary stays minimal.
+    fn record_modification(&mut self, original: ScheduleLine, modified: ScheduleLine) {
+        if let Some(i) = self.schedule_list.items.iter().position(|x| x == &original) {
+            self.schedule_list.items.remove(i);
+        }
+        if let Some(added) = self.additions.iter_mut().find(|x| **x == original) {
+            *added = modified;
+        } else if let Some(i) = self
+            .modifications
+            .iter()
+            .position(|(_, new)| *new == original)
+        {
+            if self.modifications[i].0 == modified {
+                self.modifications.remove(i);
+            } else {
+                self.modifications[i].1 = modified;
+            }
+        } else if original != modified {
+            self.modifications.push((original, modified));
+        }
+    }
+
+    /// Fills the editor inputs from the selected schedule line so that it can be modified.
+    /// Returns `false` if no line is selected.
+    pub fn start_editing(&mut self) -> bool {
+        let Some(i) = self.schedule_list.state.selected() else {
+            return false;
+        };
+        let line = self.schedule_list.items[i].clone();
+        // The start is shown in the zone times are being entered in
+        let zone = Zone::parse(&self.zone_input, &self.site()).unwrap_or_else(|_| {
+            self.zone_input = String::new();
+            Zone::Utc
+        });
+        self.start_input = zone
+            .local_time(line.timestamp)
+            .format("%Y%m%d %H:%M")
+            .to_string();
+        self.duration_input = line.duration.to_string();
+        self.end_input = String::new();
+        self.priority_input = line.priority.to_string();
+        self.kwarg_input = line.kwargs.to_string();
+        self.recurrence_input = String::new();
+
+        // Keep experiments that are no longer in the experiments directory selectable,
+        // rather than silently swapping them out for the default
+        self.clear_experiment_filter();
+        if !self
+            .all_experiments
+            .iter()
+            .any(|x| x.name == line.experiment)
+        {
+            self.all_experiments
+                .push(Experiment::unknown(&line.experiment));
+            self.all_experiments.sort_by(|a, b| a.name.cmp(&b.name));
+            self.experiment_list.items = self.all_experiments.clone();
+        }
+        self.select_experiment(&line.experiment);
+        self.mode_list.state.select(
+            self.mode_list
+                .items
+                .iter()
+                .position(|x| *x == line.scheduling_mode),
+        );
+
+        self.editing_line = Some(line);
+        self.schedule_list.unselect();
+        true
+    }
+
+    /// Abandons the entry currently in the editor. Inputs that were pre-filled from an existing
+    /// line are cleared, while inputs for a new line are kept for next time.
+    pub fn cancel_entry(&mut self) {
+        self.pending_warnings.clear();
+        self.pending_line = None;
+        self.recurrence_preview.clear();
+        if self.editing_line.take().is_some() {
+            self.start_input = String::new();
+            self.duration_input = String::new();
+            self.end_input = String::new();
+            self.priority_input = String::new();
+            self.kwarg_input = String::new();
+            self.recurrence_input = String::new();
+        }
+    }
+
+    /// Indices of the schedule lines that match the search, or of every line if there is no
+    /// search.
+    pub fn visible_lines(&self) -> Vec<usize> {
+        (0..self.schedule_list.items.len())
+            .filter(|&i| {
+                self.search
+                    .as_ref()
+                    .is_none_or(|x| x.matches(&self.schedule_list.items[i]))
+            })
+            .collect()
+    }
+
+    /// Moves the selection `steps` visible lines down, or up if negative. Past either end of the
+    /// list, the selection wraps around if `wrap` is set and stops otherwise.
+    pub fn move_selection(&mut self, steps: isize, wrap: bool) {
+        let visible = self.visible_lines();
+        if visible.is_empty() {
+            self.schedule_list.unselect();
+            return;
+        }
+        let len = visible.len() as isize;
+        let target = match self.schedule_list.state.selected() {
+            None if steps > 0 => steps - 1,
+            None => len + steps,
+            Some(i) => {
+                // A selected line that is hidden counts as being just before the next visible one
+                let position = visible.partition_point(|&x| x < i) as isize;
+                if visible.get(position as usize) == Some(&i) || steps < 0 {
+                    position + steps
+                } else {
+                    position + steps - 1
+                }
+            }
+        };
+        let target = if wrap {
+            target.rem_euclid(len)
+        } else {
+            target.clamp(0, len - 1)
+        };
+        self.schedule_list
+            .state
+            .select(Some(visible[target as usize]));
+    }
+
+    /// Selects the first visible line.
+    pub fn select_first_line(&mut self) {
+        self.schedule_list.unselect();
+        self.move_selection(1, false);
+    }
+
+    /// Selects the last visible line.
+    pub fn select_last_line(&mut self) {
+        self.schedule_list.unselect();
+        self.move_selection(-1, false);
+    }
+
+    /// Searches for lines matching `search_input`. The selected line stays selected if it
+    /// matches, otherwise the next match is selected.
+    pub fn apply_search(&mut self) -> Result<(), QueryError> {
+        let query = Query::parse(&self.search_input).inspect_err(|e| {
+            self.search_error = Some(e.clone());
+        })?;
+        self.search_error = None;
+        self.search = Some(query);
+        self.search_state = ListState::default();
+        let visible = self.visible_lines();
+        if !self
+            .schedule_list
+            .state
+            .selected()
+            .is_some_and(|i| visible.contains(&i))
+        {
+            self.move_selection(1, true);
+        }
+        Ok(())
+    }
+
+    /// Shows every line again.
+    pub fn clear_search(&mut self) {
+        self.search = None;
+        self.search_input.clear();
+        self.search_error = None;
+    }
+
+    /// Selects the experiment called `name` in the experiment list, if it is there.
+    fn select_experiment(&mut self, name: &str) {
+        self.experiment_list.state.select(
+            self.experiment_list
+                .items
+                .iter()
+                .position(|x| x.name == name),
+        );
+    }
+
+    /// Adds a character to the experiment filter.
+    pub fn push_experiment_filter(&mut self, c: char) {
+        if self.experiment_filter.is_empty() {
+            self.experiment_before_filter = self
+                .experiment_list
+                .state
+                .selected()
+                .map(|i| self.experiment_list.items[i].name.clone());
+        }
+        self.experiment_filter.push(c);
+        self.filter_experiments();
+    }
+
+    /// Removes the last character of the experiment filter.
+    pub fn pop_experiment_filter(&mut self) {
+        self.experiment_filter.pop();
+        self.filter_experiments();
+    }
+
+    /// Shows every experiment again, keeping the selected one selected.
+    pub fn clear_experiment_filter(&mut self) {
+        self.experiment_filter.clear();
+        self.filter_experiments();
+    }
+
+    /// Narrows the experiment list down to experiments whose names match the filter, best
+    /// matches first. The selected experiment stays selected if it still matches, otherwise the
+    /// best match is selected.
+    fn filter_experiments(&mut self) {
+        let selected = self
+            .experiment_list
+            .state
+            .selected()
+            .map(|i| self.experiment_list.items[i].name.clone())
+            .or(self.experiment_before_filter.clone());
+        if self.experiment_filter.is_empty() {
+            self.experiment_list.items = self.all_experiments.clone();
+            self.experiment_before_filter = None;
+        } else {
+            let mut matches: Vec<(i64, &Experiment)> = self
+                .all_experiments
+                .iter()
+                .filter_map(|x| Some((fuzzy_match(&self.experiment_filter, &x.name)?.0, x)))
+                .collect();
+            matches.sort_by_key(|(score, _)| Reverse(*score));
+            self.experiment_list.items = matches.into_iter().map(|(_, x)| x.clone()).collect();
+        }
+        match selected {
+            Some(name) if self.experiment_list.items.iter().any(|x| x.name == name) => {
+                self.select_experiment(&name)
+            }
+            _ if self.experiment_filter.is_empty() => self.experiment_list.unselect(),
+            _ => self.experiment_list.first(),
+        }
+    }
+
+    /// Checks every added or modified line against the rest of the schedule, returning any
+    /// warnings.
+    pub fn change_warnings(&self) -> Vec<(ScheduleLine, ScheduleWarning)> {
+        let now = Utc::now();
+        let mut warnings = vec![];
+        let changed = self
+            .additions
+            .iter()
+            .chain(self.modifications.iter().map(|(_, new)| new));
+        for line in changed {
+            let mut others = self.schedule_list.items.clone();
+            if let Some(i) = others.iter().position(|x| x == line) {
+                others.remove(i);
+            }
+            for warning in check_line(line, &others, now) {
+                warnings.push((line.clone(), warning));
+            }
+        }
+        warnings
+    }
+
+    /// Removes the selected unparseable line from the schedule.
+    pub fn remove_invalid_line(&mut self) {
+        if let Some(i) = self.diagnostics.state.selected() {
+            let line_number = self.diagnostics.items[i].line_number;
+            let before = self.edit_state();
+            if let Some(raw) = self.document.remove_invalid(line_number) {
+                self.removed_invalid.push(raw);
+                self.record(format!("remove invalid line {line_number}"), before);
+            }
+            self.refresh_diagnostics();
+        }
+    }
+
+    /// Starts fixing the selected unparseable line, beginning from its current text.
+    pub fn start_fix(&mut self) {
+        if let Some(i) = self.diagnostics.state.selected() {
+            self.fix_input = Some(self.diagnostics.items[i].raw.clone());
+            self.last_err = None;
+        }
+    }
+
+    /// Attempts to parse `fix_input` and put it in place of the selected unparseable line. On
+    /// success, the line is added to the schedule; on failure, the error is kept in `last_err`.
+    pub fn save_fix(&mut self) {
+        let (Some(i), Some(input)) = (self.diagnostics.state.selected(), &self.fix_input) else {
+            return;
+        };
+        match ScheduleLine::try_from(input) {
+            Err(e) => self.last_err = Some(e),
+            Ok(line) => {
+                let before = self.edit_state();
+                let line_number = self.diagnostics.items[i].line_number;
+                self.document.fix_invalid(line_number, line.clone());
+                self.additions.push(line.clone());
+                self.schedule_list.items.push(line);
+                self.schedule_list.items.sort();
+                self.schedule_list.items.reverse();
+                self.fix_input = None;
+                self.last_err = None;
+                self.refresh_diagnostics();
+                self.record(format!("fix invalid line {line_number}"), before);
+            }
+        }
+    }
+
+    /// Reloads the list of unparseable lines from the document, keeping a valid selection.
+    fn refresh_diagnostics(&mut self) {
+        self.diagnostics.items = self.document.diagnostics();
+        match self.diagnostics.state.selected() {
+            Some(i) if i < self.diagnostics.items.len() => {}
+            _ => self.diagnostics.last(),
+        }
+    }
+
+    /// Shifts the timeline window by a number of days (negative to go back in time).
+    pub fn pan_timeline(&mut self, days: i64) {
+        self.timeline_start += Duration::days(days);
+    }
+
+    /// Widens (positive) or narrows (negative) the timeline window by a number of days.
+    pub fn zoom_timeline(&mut self, days: i64) {
+        self.timeline_days = (self.timeline_days + days).clamp(1, MAX_TIMELINE_DAYS);
+    }
+
+    /// Moves the timeline window back to start at the beginning of today.
+    pub fn reset_timeline(&mut self) {
+        self.timeline_start = Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc();
+    }
+
+    /// Removes a line from the schedule. Removing a line that was added or modified since loading
+    /// the schedule cancels out that change, so that the change summary stays minimal.
     pub fn remove_schedule_line(&mut self) {
         if let Some(x) = self.schedule_list.state.selected() {
-            self.deletions.push(self.schedule_list.items.remove(x));
+            let line = self.schedule_list.items[x].clone();
+            self.apply_change(&Change::Remove(line), None);
+        }
+        self.schedule_list.unselect();
+    }
+
+    /// Records that `line` was removed from the schedule list.
+    fn record_removal(&mut self, line: &ScheduleLine) {
+        if let Some(i) = self.additions.iter().position(|y| y == line) {
+            self.additions.remove(i);
+        } else if let Some(i) = self.modifications.iter().position(|(_, new)| new == line) {
+            let (original, _) = self.modifications.remove(i);
+            self.deletions.push(original);
+        } else {
+            self.deletions.push(line.clone());
+        }
+    }
+
+    /// Finds the lines that have ended and will never run again, for confirming that they
+    /// should be archived.
+    pub fn preview_archive(&mut self) {
+        self.archive_preview = archive::ended_lines(&self.schedule_list.items, Utc::now());
+    }
+
+    /// Removes the lines found by `preview_archive` from the schedule. They are moved to the
+    /// archive file when the changes are saved, and can be undone until then.
+    pub fn archive_ended_lines(&mut self) {
+        if self.archive_preview.is_empty() {
+            return;
+        }
+        let before = self.edit_state();
+        let lines = std::mem::take(&mut self.archive_preview);
+        for line in lines.iter() {
+            if let Some(i) = self.schedule_list.items.iter().position(|x| x == line) {
+                self.schedule_list.items.remove(i);
+                self.record_removal(line);
+            }
         }
         self.schedule_list.unselect();
+        let description = format!("archive {} ended lines", lines.len());
+        self.archived.extend(lines);
+        self.record(description, before);
+    }
+
+    /// Captures the parts of the state that are changed by editing the schedule.
+    fn edit_state(&self) -> EditState {
+        EditState {
+            schedule: self.schedule_list.items.clone(),
+            document: self.document.clone(),
+            removed_invalid: self.removed_invalid.clone(),
+            additions: self.additions.clone(),
+            deletions: self.deletions.clone(),
+            modifications: self.modifications.clone(),
+            archived: self.archived.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: EditState) {
+        self.schedule_list.items = state.schedule;
+        self.schedule_list.unselect();
+        self.document = state.document;
+        self.removed_invalid = state.removed_invalid;
+        self.additions = state.additions;
+        self.deletions = state.deletions;
+        self.modifications = state.modifications;
+        self.archived = state.archived;
+        self.refresh_diagnostics();
+    }
+
+    /// Adds an edit to the undo stack, given the state from before it was made. Making a new edit
+    /// discards anything that could have been redone.
+    fn record(&mut self, description: String, before: EditState) {
+        self.undo_stack.push(Operation {
+            description,
+            before,
+            after: self.edit_state(),
+        });
+        self.redo_stack.clear();
+        self.last_change = None;
+    }
+
+    /// Undoes the most recent edit. Returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(operation) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.restore(operation.before.clone());
+        self.redo_stack.push(operation);
+        self.last_change = None;
+        true
+    }
+
+    /// Redoes the most recently undone edit. Returns `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(operation) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.restore(operation.after.clone());
+        self.undo_stack.push(operation);
+        self.last_change = None;
+        true
+    }
+
+    /// Refreshes the list of backups of the schedule and selects the most recent one.
+    pub fn open_backups(&mut self) {
+        self.backups.items = backup::list(&self.scd_path).unwrap_or_default();
+        self.backups.first();
+        self.refresh_backup_changes();
     }
 
-    /// Loads in the schedule from file.
-    pub fn load_schedule<P>(filename: P) -> Result<Vec<ScheduleLine>, Box<dyn Error>>
+    /// Selects the next backup.
+    pub fn next_backup(&mut self) {
+        self.backups.next();
+        self.refresh_backup_changes();
+    }
+
+    /// Selects the previous backup.
+    pub fn previous_backup(&mut self) {
+        self.backups.previous();
+        self.refresh_backup_changes();
+    }
+
+    /// Scrolls the changes of the selected backup `lines` lines down, or up if negative.
+    pub fn scroll_backup_changes(&mut self, lines: i32) {
+        let last = match &self.backup_changes {
+            Ok(changes) => changes.len().saturating_sub(1),
+            Err(_) => 0,
+        };
+        let scroll = (self.backup_scroll as i32 + lines).clamp(0, last as i32);
+        self.backup_scroll = u16::try_from(scroll).unwrap_or(u16::MAX);
+    }
+
+    /// Works out the lines that restoring the selected backup would change in the schedule as
+    /// edited so far.
+    fn refresh_backup_changes(&mut self) {
+        self.backup_scroll = 0;
+        let Some(i) = self.backups.state.selected() else {
+            self.backup_changes = Ok(vec![]);
+            return;
+        };
+        self.backup_changes = self.backups.items[i]
+            .read()
+            .map(|backup| {
+                diff(&self.edited_document().to_string(), &backup)
+                    .into_iter()
+                    .filter(|x| !matches!(x, DiffLine::Same(_)))
+                    .collect()
+            })
+            .map_err(|e| e.to_string());
+    }
+
+    /// Replaces the schedule being edited with the contents of the selected backup. The restored
+    /// schedule is not written until the changes are saved, and can be undone like any other
+    /// edit.
+    pub fn restore_backup(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(i) = self.backups.state.selected() else {
+            return Ok(());
+        };
+        let backup = &self.backups.items[i];
+        let mut document = ScdDocument::parse(&backup.read()?);
+        document.set_path(&backup.path);
+        let description = format!("restore backup from {}", backup.time.format("%Y%m%d %H:%M"));
+
+        let before = self.edit_state();
+        let lines = document.lines();
+        self.additions = multiset_difference(&lines, &self.base.lines());
+        self.deletions = multiset_difference(&self.base.lines(), &lines);
+        self.modifications.clear();
+        self.removed_invalid =
+            multiset_difference(&invalid_text(&self.base), &invalid_text(&document));
+        self.schedule_list.items = lines;
+        self.schedule_list.items.sort();
+        self.schedule_list.items.reverse();
+        self.schedule_list.unselect();
+        self.document = document;
+        self.refresh_diagnostics();
+        self.record(description, before);
+        Ok(())
+    }
+
+    /// Loads in the schedule from file, failing on the first line that cannot be parsed.
+    pub fn load_schedule<P>(filename: P) -> Result<ScdDocument, Box<dyn Error>>
     where
         P: AsRef<Path>,
     {
-        let schedule_file = File::open(filename)?;
+        let document = Self::load_schedule_lenient(filename)?;
+        match document.diagnostics().into_iter().next() {
+            Some(diagnostic) => Err(diagnostic.into()),
+            None => Ok(document),
+        }
+    }
 
-        let mut schedule_lines = vec![];
-        for line in io::BufReader::new(schedule_file).lines().flatten() {
-            schedule_lines.extend([ScheduleLine::try_from(&line)?]);
+    /// Loads in the schedule from file, keeping lines that cannot be parsed so that they can be
+    /// reported with `ScdDocument::diagnostics`.
+    pub fn load_schedule_lenient<P>(filename: P) -> Result<ScdDocument, Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::read_schedule(filename)?.0)
+    }
+
+    /// Loads in the schedule from file like `load_schedule_lenient`, along with a stamp of the
+    /// file for noticing later changes.
+    pub fn read_schedule<P>(filename: P) -> Result<(ScdDocument, FileStamp), Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let metadata = std::fs::metadata(&filename)?;
+        let text = std::fs::read_to_string(&filename)?;
+        let mut document = ScdDocument::parse(&text);
+        document.set_path(filename.as_ref());
+        Ok((document, FileStamp::new(&text, &metadata)))
+    }
+
+    /// Checks whether someone else has changed the schedule file since it was loaded. If so, our
+    /// changes are merged onto the new version of the file and kept in `merge` for review, and
+    /// `true` is returned. The file is only read if its modification time or size has changed.
+    pub fn check_for_external_changes(&mut self) -> Result<bool, Box<dyn Error>> {
+        if self.base_stamp.looks_unchanged(&self.scd_path) {
+            self.merge = None;
+            return Ok(false);
         }
-        schedule_lines.reverse();
-        Ok(schedule_lines)
+        let (mut theirs, stamp) = Self::read_schedule(&self.scd_path)?;
+        if !stamp.changed(&self.base_stamp) {
+            // Only touched, so the new modification time saves reading it again next time
+            self.base_stamp = stamp;
+            self.merge = None;
+            return Ok(false);
+        }
+
+        // Unparseable lines that we removed or fixed
+        let resolved_invalid =
+            multiset_difference(&invalid_text(&self.base), &invalid_text(&self.document));
+
+        let conflicts = theirs.merge(
+            &self.additions,
+            &self.deletions,
+            &self.modifications,
+            &resolved_invalid,
+        );
+        self.merge = Some(Merge {
+            document: theirs,
+            conflicts,
+            stamp,
+        });
+        Ok(true)
     }
 
-    /// Saves the schedule to file, making a backup of the current schedule first.
+    /// Saves the schedule to file, making a backup of the current schedule first. Only lines
+    /// that have changed since the schedule was loaded are rewritten. If our changes have been
+    /// merged onto a newer version of the file, the merged version is saved instead.
     pub fn save_schedule(&self) -> Result<(), Box<dyn Error>> {
-        let mut backup_file = self.scd_path.clone();
-        backup_file.set_extension("scd.bak");
-        std::fs::copy(&self.scd_path, backup_file)?;
+        let document = match &self.merge {
+            Some(merge) => merge.document.clone(),
+            None => self.edited_document(),
+        };
+        // Archive first, so that archived lines are never lost if saving fails
+        if !self.archived.is_empty() {
+            archive::append(&self.scd_path, &self.archived, Utc::now().date_naive())?;
+        }
+        Self::write_schedule(&self.scd_path, &document, self.keep_backups)?;
+        Ok(())
+    }
+
+    /// Site ID of the schedule, taken from the file name.
+    pub fn site(&self) -> String {
+        self.scd_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Whether saving would change the schedule file.
+    pub fn has_changes(&self) -> bool {
+        self.edited_document() != self.base
+    }
+
+    /// The schedule document with all changes so far applied.
+    pub fn edited_document(&self) -> ScdDocument {
+        let mut document = self.document.clone();
+        document.update(&self.schedule_list.items, &self.modifications);
+        document
+    }
+
+    /// Writes `document` to file, making a backup of the current schedule first and keeping only
+    /// the `keep_backups` most recent backups. Returns the path of the backup, if one was made.
+    ///
+    /// The schedule is written to a temporary file in the same directory, which is synced to
+    /// disk and read back to check that it holds the same schedule before it is renamed over the
+    /// original. The schedule file is therefore never left half written.
+    pub fn write_schedule<P>(
+        filename: P,
+        document: &ScdDocument,
+        keep_backups: usize,
+    ) -> Result<Option<PathBuf>, Box<dyn Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let filename = filename.as_ref();
+        let backup = backup::create(filename, keep_backups)?;
+
+        let mut temp_name = OsString::from(".");
+        temp_name.push(filename.file_name().unwrap_or_default());
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_file = filename.with_file_name(temp_name);
 
-        let mut schedule_file = File::create(&self.scd_path)?;
-        for line in self.schedule_list.items.iter().rev() {
-            let mut chars = line.format().into_bytes();
-            chars.push(b'\n');
-            schedule_file.write_all(&*chars)?;
+        let result = Self::write_verified(&temp_file, filename, document);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_file);
+        }
+        result.map(|()| backup)
+    }
+
+    /// Writes `document` to `temp_file`, then renames it over `filename` once it is verified.
+    fn write_verified(
+        temp_file: &Path,
+        filename: &Path,
+        document: &ScdDocument,
+    ) -> Result<(), Box<dyn Error>> {
+        let text = document.to_string();
+        let mut file = File::create(temp_file)?;
+        file.write_all(text.as_bytes())?;
+        file.set_permissions(std::fs::metadata(filename)?.permissions())?;
+        file.sync_all()?;
+        drop(file);
+
+        let written = Self::load_schedule_lenient(temp_file)?;
+        if written.to_string() != text || written.lines() != document.lines() {
+            return Err(format!(
+                "{} did not read back the same as the schedule that was written",
+                temp_file.display()
+            )
+            .into());
+        }
+
+        std::fs::rename(temp_file, filename)?;
+        // Make sure the rename itself survives a crash
+        if let Some(dir) = filename.parent().filter(|x| !x.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
         }
         Ok(())
     }
 }
 
-/// Loads in the names of all experiments (files) in `dir`, ignoring non-experiment files.
-fn load_experiments<P>(dir: P) -> Result<Vec<String>, Box<dyn Error>>
-where
-    P: AsRef<Path>,
-{
-    let mut files: Vec<String> = vec![];
-    let ignored_files = [
-        ".git",
-        ".gitignore",
-        "__init__",
-        "superdarn_common_fields",
-        "LICENSE",
-        "README",
-    ];
-    for entry in std::fs::read_dir(dir)? {
-        if let Ok(x) = entry {
-            if !x.metadata().unwrap().is_file() {
-                continue;
+/// Items of `a` that are not in `b`, counting repeated items separately.
+fn multiset_difference<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut remaining = b.to_vec();
+    let mut difference = vec![];
+    for x in a.iter() {
+        match remaining.iter().position(|y| y == x) {
+            Some(i) => {
+                remaining.remove(i);
             }
-            let stripped_path = x.path().with_extension("");
-            let filename = stripped_path.file_name().unwrap();
-            if ignored_files.contains(&filename.to_str().unwrap()) {
-                continue;
-            }
-            files.push(filename.to_str().unwrap().to_string());
+            None => difference.push(x.clone()),
         }
     }
-    files.sort();
+    difference
+}
+
+/// Text of every line of `document` that could not be parsed.
+fn invalid_text(document: &ScdDocument) -> Vec<String> {
+    document.diagnostics().into_iter().map(|x| x.raw).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    const SCHEDULE: &str = "20200101 00:00 - 0 normalscan common\n\
+        20250101 00:00 60 10 twofsound special\n";
+
+    fn line(text: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&text.to_string()).unwrap()
+    }
+
+    /// An app editing a copy of `SCHEDULE` in `dir`.
+    fn app(dir: &TempDir) -> App {
+        let path = dir.path().join("sas.scd");
+        fs::write(&path, SCHEDULE).unwrap();
+        App::new(path, dir.path().into(), false, 0).unwrap()
+    }
+
+    #[test]
+    fn edits_fold_into_earlier_changes() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let a = line("20250101 00:00 60 10 twofsound special");
+        let b = line("20250101 00:00 120 10 twofsound special");
+        let c = line("20250101 00:00 120 15 twofsound special");
+
+        // A line edited twice is one modification from the original to the latest
+        assert!(app.apply_change(&Change::Edit(a.clone(), b.clone()), None));
+        assert!(app.apply_change(&Change::Edit(b.clone(), c.clone()), None));
+        assert_eq!(app.modifications, vec![(a.clone(), c.clone())]);
+        assert!(app.additions.is_empty() && app.deletions.is_empty());
+
+        // Editing it back to the original leaves nothing to save
+        assert!(app.apply_change(&Change::Edit(c, a.clone()), None));
+        assert!(app.modifications.is_empty());
+        assert!(app.additions.is_empty() && app.deletions.is_empty());
+        assert!(!app.has_changes());
+
+        // An edited addition is still a single addition
+        let added = line("20260101 00:00 60 10 full_fov special");
+        let edited = line("20260101 00:00 90 10 full_fov special");
+        assert!(app.apply_change(&Change::Add(added.clone()), None));
+        assert!(app.apply_change(&Change::Edit(added, edited.clone()), None));
+        assert_eq!(app.additions, vec![edited.clone()]);
+        assert!(app.modifications.is_empty() && app.deletions.is_empty());
+        assert_eq!(app.schedule_list.items[0], edited);
+        assert_eq!(app.schedule_list.items.len(), 3);
+    }
+
+    #[test]
+    fn move_selection_through_search_matches() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        app.schedule_list.items = [
+            "20250105 00:00 60 10 twofsound special",
+            "20250104 00:00 60 10 normalscan special",
+            "20250103 00:00 60 10 twofsound special",
+            "20250102 00:00 60 10 full_fov special",
+            "20250101 00:00 60 10 twofsound special",
+        ]
+        .map(line)
+        .to_vec();
+        let select = |app: &mut App, i| app.schedule_list.state.select(Some(i));
+        let selected = |app: &App| app.schedule_list.state.selected();
+
+        // A selected line that the search hides is replaced by the next match
+        select(&mut app, 1);
+        app.search_input = "twofsound".to_string();
+        app.apply_search().unwrap();
+        assert_eq!(app.visible_lines(), vec![0, 2, 4]);
+        assert_eq!(selected(&app), Some(2));
+
+        // Moving from a hidden line goes to the match on either side of it
+        select(&mut app, 3);
+        app.move_selection(1, true);
+        assert_eq!(selected(&app), Some(4));
+        select(&mut app, 3);
+        app.move_selection(-1, true);
+        assert_eq!(selected(&app), Some(2));
+
+        // n and N wrap around, paging stops at the ends
+        app.move_selection(1, true);
+        app.move_selection(1, true);
+        assert_eq!(selected(&app), Some(0));
+        app.move_selection(-1, true);
+        assert_eq!(selected(&app), Some(4));
+        app.move_selection(-30, false);
+        assert_eq!(selected(&app), Some(0));
+        app.schedule_list.unselect();
+        app.move_selection(-1, true);
+        assert_eq!(selected(&app), Some(4));
+
+        // Nothing is selected when nothing matches
+        app.search_input = "exp:nothing".to_string();
+        app.apply_search().unwrap();
+        assert!(app.visible_lines().is_empty());
+        assert_eq!(selected(&app), None);
+        app.move_selection(1, true);
+        assert_eq!(selected(&app), None);
+
+        app.clear_search();
+        assert_eq!(app.visible_lines().len(), 5);
+    }
+
+    #[test]
+    fn filter_experiments_keeps_selection() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        app.all_experiments = ["full_fov", "normalscan", "twofsound", "twofsound_rx"]
+            .map(Experiment::unknown)
+            .to_vec();
+        app.experiment_list.items = app.all_experiments.clone();
+        let selected = |app: &App| {
+            app.experiment_list
+                .state
+                .selected()
+                .map(|i| app.experiment_list.items[i].name.clone())
+        };
+        let type_filter = |app: &mut App, text: &str| {
+            for c in text.chars() {
+                app.push_experiment_filter(c);
+            }
+        };
+
+        // The selected experiment stays selected while it matches
+        app.select_experiment("normalscan");
+        type_filter(&mut app, "nrm");
+        assert_eq!(app.experiment_list.items.len(), 1);
+        assert_eq!(selected(&app).as_deref(), Some("normalscan"));
+        app.clear_experiment_filter();
+        assert_eq!(app.experiment_list.items.len(), 4);
+        assert_eq!(selected(&app).as_deref(), Some("normalscan"));
+
+        // Otherwise the best match is selected, and stays selected when the filter is cleared
+        type_filter(&mut app, "twof");
+        assert_eq!(app.experiment_list.items.len(), 2);
+        assert_eq!(selected(&app).as_deref(), Some("twofsound"));
+        app.clear_experiment_filter();
+        assert_eq!(selected(&app).as_deref(), Some("twofsound"));
+
+        // When nothing matches, clearing the filter goes back to the experiment from before it
+        app.select_experiment("full_fov");
+        type_filter(&mut app, "xyz");
+        assert!(app.experiment_list.items.is_empty());
+        assert_eq!(selected(&app), None);
+        app.pop_experiment_filter();
+        app.clear_experiment_filter();
+        assert_eq!(selected(&app).as_deref(), Some("full_fov"));
+        assert_eq!(app.experiment_before_filter, None);
+    }
+
+    #[test]
+    fn unknown_kwargs_need_confirming() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let source = "class TwoFSound:\n    def __init__(self, embargo=False, **kwargs):\n";
+        let mut experiment = Experiment::parse("twofsound", source).unwrap();
+        experiment.path = Some(dir.path().join("twofsound.py"));
+        app.all_experiments = vec![experiment];
+        app.experiment_list.items = app.all_experiments.clone();
+        app.select_experiment("twofsound");
+        let fill_inputs = |app: &mut App| {
+            app.start_input = "20300101 00:00".to_string();
+            app.duration_input = "60".to_string();
+            app.priority_input = "10".to_string();
+            app.kwarg_input = "--embrago".to_string();
+        };
+
+        // The experiment takes **kwargs, so an unknown kwarg is added once confirmed
+        fill_inputs(&mut app);
+        assert_eq!(app.save_entry(), Ok(false));
+        assert_eq!(
+            app.pending_warnings,
+            vec![ScheduleWarning::UnknownKwarg("--embrago".to_string())]
+        );
+        assert_eq!(app.save_entry(), Ok(true));
+        assert_eq!(
+            app.additions,
+            vec![line("20300101 00:00 60 10 twofsound common --embrago")]
+        );
+
+        // Without **kwargs it cannot be added at all
+        app.experiment_list.items[0].var_kwargs = false;
+        fill_inputs(&mut app);
+        assert_eq!(app.save_entry().unwrap_err().field(), Field::Kwargs);
+        assert!(app.pending_warnings.is_empty());
+    }
 
-    Ok(files)
+    #[test]
+    fn warnings_are_confirmed_for_one_line() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        app.start_input = "20200601 00:00".to_string();
+        app.duration_input = "60".to_string();
+        app.priority_input = "10".to_string();
+        assert_eq!(app.save_entry(), Ok(false));
+        assert_eq!(app.pending_warnings, vec![ScheduleWarning::InPast]);
+
+        // A different line with the same warnings needs confirming again
+        app.duration_input = "90".to_string();
+        assert_eq!(app.save_entry(), Ok(false));
+        assert_eq!(app.schedule_list.items.len(), 2);
+        assert_eq!(app.save_entry(), Ok(true));
+        assert_eq!(
+            app.additions,
+            vec![line("20200601 00:00 90 10 normalscan common")]
+        );
+        assert!(app.pending_warnings.is_empty() && app.pending_line.is_none());
+    }
+
+    #[test]
+    fn lines_already_in_schedule_are_rejected() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let existing = line("20250101 00:00 60 10 twofsound special");
+        let later = line("20260101 00:00 60 10 twofsound special");
+        assert!(app.apply_change(&Change::Add(later.clone()), None));
+        let select = |app: &mut App, line: &ScheduleLine| {
+            let i = app.schedule_list.items.iter().position(|x| x == line);
+            app.schedule_list.state.select(i);
+        };
+
+        // Adding a copy of a line keeps the inputs for the operator to change
+        select(&mut app, &existing);
+        assert!(app.start_editing());
+        app.editing_line = None;
+        let e = app.save_entry().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Expected a line not already in the schedule, \
+            got 20250101 00:00 60 10 twofsound special"
+        );
+        assert_eq!(app.last_err, Some(e));
+        assert_eq!(app.start_input, "20250101 00:00");
+        assert_eq!(app.schedule_list.items.len(), 3);
+
+        // Editing a line into another one would leave two copies of it
+        select(&mut app, &later);
+        assert!(app.start_editing());
+        app.start_input = "20250101 00:00".to_string();
+        assert!(app.save_entry().is_err());
+        assert_eq!(app.editing_line, Some(later.clone()));
+        assert_eq!(app.schedule_list.items.len(), 3);
+        assert!(!app.apply_change(&Change::Edit(later.clone(), existing.clone()), None));
+        assert_eq!(app.additions, vec![later]);
+        assert!(app.modifications.is_empty());
+    }
+
+    /// Names of the files in `dir`, sorted.
+    fn file_names(dir: &TempDir) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn write_schedule_replaces_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sas.scd");
+        fs::write(&path, SCHEDULE).unwrap();
+        let mut document = ScdDocument::parse(SCHEDULE);
+        document.insert(line("20260101 00:00 60 10 full_fov special"));
+
+        App::write_schedule(&path, &document, 2).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), document.to_string());
+        // Nothing but the schedule and its backup is left behind
+        let backups = backup::list(&path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].read().unwrap(), SCHEDULE);
+        assert_eq!(
+            file_names(&dir),
+            vec!["sas.scd".to_string(), backups[0].name()]
+        );
+    }
+
+    #[test]
+    fn write_schedule_failing_verification() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sas.scd");
+        fs::write(&path, SCHEDULE).unwrap();
+
+        // A line that does not read back the same as it was written
+        let mut document = ScdDocument::parse(SCHEDULE);
+        let mut bad = line("20260101 00:00 60 10 full_fov special");
+        bad.experiment = "full fov".to_string();
+        document.insert(bad);
+
+        let e = App::write_schedule(&path, &document, 0).unwrap_err();
+        assert!(e.to_string().contains("did not read back the same"), "{e}");
+        assert_eq!(fs::read_to_string(&path).unwrap(), SCHEDULE);
+        assert_eq!(file_names(&dir), vec!["sas.scd"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_schedule_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sas.scd");
+        fs::write(&path, SCHEDULE).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        App::write_schedule(&path, &ScdDocument::parse(SCHEDULE), 0).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn backup_changes_follow_selection() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let mut document = ScdDocument::parse(SCHEDULE);
+        document.insert(line("20260101 00:00 60 10 full_fov special"));
+        App::write_schedule(&app.scd_path, &document, 2).unwrap();
+
+        // The only backup holds the schedule as it was loaded, so restoring it changes nothing
+        app.open_backups();
+        assert_eq!(app.backup_changes, Ok(vec![]));
+
+        app.apply_change(
+            &Change::Remove(line("20200101 00:00 - 0 normalscan common")),
+            None,
+        );
+        app.apply_change(
+            &Change::Add(line("20270101 00:00 60 10 full_fov special")),
+            None,
+        );
+        // Selecting a backup works out its changes against the schedule as edited
+        app.next_backup();
+        assert_eq!(
+            app.backup_changes,
+            Ok(vec![
+                DiffLine::Added("20200101 00:00 - 0 normalscan common".to_string()),
+                DiffLine::Removed("20270101 00:00 60 10 full_fov special".to_string()),
+            ])
+        );
+        app.scroll_backup_changes(10);
+        assert_eq!(app.backup_scroll, 1);
+        app.scroll_backup_changes(-10);
+        assert_eq!(app.backup_scroll, 0);
+    }
+
+    #[test]
+    fn merge_external_changes() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        assert!(!app.check_for_external_changes().unwrap());
+        assert!(app.merge.is_none());
+
+        let special = line("20250101 00:00 60 10 twofsound special");
+        let longer = line("20250101 00:00 120 10 twofsound special");
+        let ours = line("20260101 00:00 60 10 full_fov special");
+        assert!(app.apply_change(&Change::Edit(special.clone(), longer.clone()), None));
+        assert!(app.apply_change(&Change::Add(ours), None));
+
+        // Rewriting the file without changing it is not a change
+        fs::write(&app.scd_path, SCHEDULE).unwrap();
+        assert!(!app.check_for_external_changes().unwrap());
+
+        // Someone else removes the line we edited and adds one of their own
+        fs::write(
+            &app.scd_path,
+            "20200101 00:00 - 0 normalscan common\n\
+            # Added by someone else\n\
+            20270101 00:00 60 10 normalscan special\n",
+        )
+        .unwrap();
+        assert!(app.check_for_external_changes().unwrap());
+        let merge = app.merge.as_ref().unwrap();
+        // The edited line can only be added back, and is reported as a conflict
+        assert_eq!(
+            merge.document.to_string(),
+            "20200101 00:00 - 0 normalscan common\n\
+            20250101 00:00 120 10 twofsound special\n\
+            20260101 00:00 60 10 full_fov special\n\
+            # Added by someone else\n\
+            20270101 00:00 60 10 normalscan special\n"
+        );
+        assert_eq!(
+            merge.conflicts,
+            vec![MergeConflict {
+                original: special,
+                modified: longer,
+            }]
+        );
+    }
+
+    #[test]
+    fn undo_and_redo_add() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let original = app.schedule_list.items.clone();
+        let added = line("20260101 00:00 60 10 full_fov special");
+
+        assert!(app.apply_change(&Change::Add(added.clone()), None));
+        let edited = app.schedule_list.items.clone();
+        assert_eq!(edited[0], added);
+
+        assert!(app.undo());
+        assert!(app.additions.is_empty());
+        assert_eq!(app.schedule_list.items, original);
+        assert!(!app.has_changes());
+
+        assert!(app.redo());
+        assert_eq!(app.additions, vec![added]);
+        assert!(app.deletions.is_empty());
+        assert!(app.modifications.is_empty());
+        assert_eq!(app.schedule_list.items, edited);
+        assert!(!app.redo());
+    }
+
+    #[test]
+    fn undo_edit_and_remove() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let original = app.schedule_list.items.clone();
+        let special = line("20250101 00:00 60 10 twofsound special");
+        let longer = line("20250101 00:00 120 10 twofsound special");
+
+        assert!(app.apply_change(&Change::Edit(special.clone(), longer.clone()), None));
+        assert_eq!(app.modifications, vec![(special.clone(), longer)]);
+        assert!(app.undo());
+        assert!(app.modifications.is_empty());
+        assert!(app.additions.is_empty() && app.deletions.is_empty());
+        assert_eq!(app.schedule_list.items, original);
+
+        assert!(app.apply_change(&Change::Remove(special.clone()), None));
+        assert_eq!(app.deletions, vec![special]);
+        assert_eq!(app.schedule_list.items.len(), 1);
+        assert!(app.undo());
+        assert!(app.deletions.is_empty());
+        assert!(app.additions.is_empty() && app.modifications.is_empty());
+        assert_eq!(app.schedule_list.items, original);
+        assert!(!app.has_changes());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let dir = tempdir().unwrap();
+        let mut app = app(&dir);
+        let first = line("20260101 00:00 60 10 full_fov special");
+        let second = line("20260201 00:00 60 10 full_fov special");
+
+        assert!(app.apply_change(&Change::Add(first), None));
+        assert!(app.undo());
+        assert_eq!(app.redo_stack.len(), 1);
+        assert!(app.apply_change(&Change::Add(second.clone()), None));
+        assert!(app.redo_stack.is_empty());
+        assert!(!app.redo());
+
+        assert_eq!(app.additions, vec![second.clone()]);
+        assert!(app.deletions.is_empty() && app.modifications.is_empty());
+        assert_eq!(app.schedule_list.items.len(), 3);
+        assert_eq!(app.schedule_list.items[0], second);
+    }
 }
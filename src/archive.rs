@@ -0,0 +1,133 @@
+//! Archiving of schedule lines that have ended.
+//!
+//! Lines that will never run again are moved out of the schedule into an archive file next to
+//! it, named after the schedule and the day the lines were archived, e.g.
+//! `sas.scd.20250101.archive`. Archive files are written in the schedule format, so archived
+//! lines can be copied back into a schedule if need be.
+
+use crate::schedule::{ScdDuration, ScheduleLine};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Lines of `lines` that will never run again after `now`. These are finite lines that ended at
+/// or before `now`, and infinite lines that have been replaced as the baseline by a newer
+/// infinite line of at least the same priority that had already started by `now`.
+pub fn ended_lines(lines: &[ScheduleLine], now: DateTime<Utc>) -> Vec<ScheduleLine> {
+    lines
+        .iter()
+        .filter(|line| match line.end_time() {
+            Some(end) => end <= now,
+            None => lines.iter().any(|other| {
+                other.duration == ScdDuration::Infinite
+                    && other.priority >= line.priority
+                    && other.timestamp > line.timestamp
+                    && other.timestamp <= now
+            }),
+        })
+        .cloned()
+        .collect()
+}
+
+/// Path of the archive file for lines archived from `schedule` on `date`.
+fn archive_path(schedule: &Path, date: NaiveDate) -> PathBuf {
+    let mut name = schedule.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.archive", date.format("%Y%m%d")));
+    schedule.with_file_name(name)
+}
+
+/// Appends `lines` to the archive file for `schedule` on `date`, oldest first, creating it if
+/// need be. Returns the path of the archive file.
+pub fn append(schedule: &Path, lines: &[ScheduleLine], date: NaiveDate) -> io::Result<PathBuf> {
+    let path = archive_path(schedule, date);
+    let mut file = OpenOptions::new().append(true).create(true).open(&path)?;
+    let mut text = String::new();
+    if file.metadata()?.len() == 0 {
+        text.push_str(&format!(
+            "# Lines archived from {} on {}\n",
+            schedule.file_name().unwrap_or_default().to_string_lossy(),
+            date.format("%Y%m%d")
+        ));
+    }
+    let mut lines = lines.to_vec();
+    lines.sort();
+    for line in lines.iter() {
+        text.push_str(&line.format());
+        text.push('\n');
+    }
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::parse_timestamp;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn lines(text: &[&str]) -> Vec<ScheduleLine> {
+        text.iter()
+            .map(|x| ScheduleLine::try_from(&x.to_string()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn finds_ended_lines() {
+        let schedule = lines(&[
+            "20200101 00:00 - 0 twofsound common",
+            "20240101 00:00 - 0 normalscan common",
+            "20250101 00:00 - 0 full_fov common",
+            "20240301 00:00 60 10 twofsound special",
+            "20240301 12:00 1440 10 full_fov special",
+        ]);
+        let now = parse_timestamp("20240302 00:00").unwrap();
+        assert_eq!(
+            ended_lines(&schedule, now),
+            lines(&[
+                "20200101 00:00 - 0 twofsound common",
+                "20240301 00:00 60 10 twofsound special",
+            ])
+        );
+
+        // A line ending right now has ended, and a future baseline replaces nothing yet
+        let now = parse_timestamp("20240302 12:00").unwrap();
+        assert_eq!(ended_lines(&schedule, now).len(), 3);
+        let now = parse_timestamp("20250101 00:00").unwrap();
+        assert_eq!(ended_lines(&schedule, now).len(), 4);
+    }
+
+    #[test]
+    fn appends_to_dated_archive() {
+        let dir = tempdir().unwrap();
+        let schedule = dir.path().join("sas.scd");
+        let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+
+        let path = append(
+            &schedule,
+            &lines(&[
+                "20240301 00:00 60 10 twofsound special",
+                "20200101 00:00 - 0 twofsound common",
+            ]),
+            date,
+        )
+        .unwrap();
+        append(
+            &schedule,
+            &lines(&["20240101 00:00 - 0 normalscan common"]),
+            date,
+        )
+        .unwrap();
+
+        assert_eq!(path, dir.path().join("sas.scd.20250101.archive"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Lines archived from sas.scd on 20250101\n\
+            20200101 00:00 - 0 twofsound common\n\
+            20240301 00:00 60 10 twofsound special\n\
+            20240101 00:00 - 0 normalscan common\n"
+        );
+    }
+}
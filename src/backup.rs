@@ -0,0 +1,119 @@
+//! Timestamped backups of schedule files.
+//!
+//! A backup of the schedule is made before every save, next to the schedule and named after it
+//! and the time it was made, e.g. `sas.scd.20250101-120000.000.bak`. Only the most recent backups
+//! are kept.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Number of backups kept of each schedule, unless configured otherwise.
+pub const DEFAULT_KEEP_BACKUPS: usize = 10;
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+/// A backup of a schedule file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub time: DateTime<Utc>,
+}
+impl Backup {
+    /// Reads the contents of the backup.
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+
+    /// File name of the backup.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Copies the schedule at `schedule` to a new backup, then removes all but the `keep` most recent
+/// backups. Returns the path of the new backup, or `None` if nothing was backed up because
+/// `keep` is 0.
+pub fn create(schedule: &Path, keep: usize) -> io::Result<Option<PathBuf>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let name = format!(
+        "{}.{}.bak",
+        file_name(schedule),
+        Utc::now().format(TIME_FORMAT)
+    );
+    let path = schedule.with_file_name(name);
+    fs::copy(schedule, &path)?;
+    for backup in list(schedule)?.iter().skip(keep) {
+        fs::remove_file(&backup.path)?;
+    }
+    Ok(Some(path))
+}
+
+/// Finds the backups of the schedule at `schedule`, most recent first.
+pub fn list(schedule: &Path) -> io::Result<Vec<Backup>> {
+    let prefix = format!("{}.", file_name(schedule));
+    let dir = match schedule.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x,
+        _ => Path::new("."),
+    };
+    let mut backups = vec![];
+    for entry in fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(time) = name
+            .strip_prefix(&prefix)
+            .and_then(|x| x.strip_suffix(".bak"))
+            .and_then(|x| NaiveDateTime::parse_from_str(x, TIME_FORMAT).ok())
+        else {
+            continue;
+        };
+        backups.push(Backup {
+            path: schedule.with_file_name(name),
+            time: time.and_utc(),
+        });
+    }
+    backups.sort_by_key(|x| std::cmp::Reverse(x.time));
+    Ok(backups)
+}
+
+fn file_name(schedule: &Path) -> String {
+    schedule
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn keeps_most_recent_backups() {
+        let dir = tempdir().unwrap();
+        let schedule = dir.path().join("sas.scd");
+
+        for i in 0..4 {
+            fs::write(&schedule, format!("version {i}\n")).unwrap();
+            create(&schedule, 3).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        create(&schedule, 0).unwrap();
+        fs::write(dir.path().join("sas.scd.bak"), "old style backup").unwrap();
+
+        let backups = list(&schedule).unwrap();
+        assert_eq!(
+            backups
+                .iter()
+                .map(|x| x.read().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["version 3\n", "version 2\n", "version 1\n"]
+        );
+        assert!(backups.windows(2).all(|x| x[0].time > x[1].time));
+    }
+}
@@ -0,0 +1,238 @@
+//! Campaigns, which schedule the same lines on the schedules of many sites at once.
+//!
+//! A campaign file is written in TOML, naming the sites and giving a `[[line]]` table for each
+//! schedule line:
+//!
+//! ```toml
+//! sites = ["sas", "pgr", "rkn"]
+//!
+//! [[line]]
+//! start = "20250408 18:00"
+//! duration = 120        # minutes, or "-" to run until replaced
+//! priority = 15
+//! experiment = "twofsound"
+//! mode = "special"
+//! kwargs = "--embargo"  # optional
+//! ```
+
+use crate::schedule::conflict::{check_line, ScheduleWarning};
+use crate::schedule::document::ScdDocument;
+use crate::schedule::{ScheduleError, ScheduleLine};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CampaignError {
+    #[error("{0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("line {number} of the campaign: {error}")]
+    Line {
+        number: usize,
+        error: Box<ScheduleError>,
+    },
+
+    #[error("the campaign names no sites")]
+    NoSites,
+
+    #[error("the campaign has no lines")]
+    NoLines,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CampaignFile {
+    sites: Vec<String>,
+    #[serde(rename = "line", default)]
+    lines: Vec<LineTable>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LineTable {
+    start: String,
+    duration: DurationValue,
+    priority: u8,
+    experiment: String,
+    mode: String,
+    #[serde(default)]
+    kwargs: String,
+}
+
+/// A duration in minutes, or `"-"` for lines that run until replaced.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DurationValue {
+    Minutes(u32),
+    Text(String),
+}
+impl Display for DurationValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Minutes(x) => write!(f, "{x}"),
+            Self::Text(x) => f.write_str(x),
+        }
+    }
+}
+
+/// Schedule lines to add to the schedules of several sites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Campaign {
+    pub sites: Vec<String>,
+    pub lines: Vec<ScheduleLine>,
+}
+
+/// The changes a campaign makes to the schedule of one site.
+pub struct SitePlan {
+    /// The schedule with the campaign's lines added.
+    pub document: ScdDocument,
+    /// Lines that were already in the schedule, which are not added again.
+    pub skipped: Vec<ScheduleLine>,
+    pub warnings: Vec<(ScheduleLine, ScheduleWarning)>,
+}
+
+impl Campaign {
+    /// Reads a campaign file. Each line is checked in the same way as a line of a schedule.
+    pub fn parse(text: &str) -> Result<Campaign, CampaignError> {
+        let file: CampaignFile = toml::from_str(text)?;
+        if file.sites.is_empty() {
+            return Err(CampaignError::NoSites);
+        }
+        if file.lines.is_empty() {
+            return Err(CampaignError::NoLines);
+        }
+        let mut sites: Vec<String> = vec![];
+        for site in file.sites {
+            if !sites.contains(&site) {
+                sites.push(site);
+            }
+        }
+        let lines = file
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let text = format!(
+                    "{} {} {} {} {} {}",
+                    x.start, x.duration, x.priority, x.experiment, x.mode, x.kwargs
+                );
+                ScheduleLine::try_from(&text).map_err(|error| CampaignError::Line {
+                    number: i + 1,
+                    error: Box::new(error),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Campaign { sites, lines })
+    }
+
+    /// Adds the campaign's lines to `document`, checking each one against the rest of the
+    /// schedule, including the campaign lines before it.
+    pub fn plan(&self, document: &ScdDocument, now: DateTime<Utc>) -> SitePlan {
+        let mut plan = SitePlan {
+            document: document.clone(),
+            skipped: vec![],
+            warnings: vec![],
+        };
+        for line in self.lines.iter() {
+            let lines = plan.document.lines();
+            if lines.contains(line) {
+                plan.skipped.push(line.clone());
+                continue;
+            }
+            for warning in check_line(line, &lines, now) {
+                plan.warnings.push((line.clone(), warning));
+            }
+            plan.document.insert(line.clone());
+        }
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::parse_timestamp;
+
+    const CAMPAIGN: &str = r#"
+        sites = ["sas", "pgr", "sas"]
+
+        [[line]]
+        start = "20250408 18:00"
+        duration = 120
+        priority = 15
+        experiment = "twofsound"
+        mode = "special"
+        kwargs = "--embargo"
+
+        [[line]]
+        start = "20250409 00:00"
+        duration = "-"
+        priority = 0
+        experiment = "normalscan"
+        mode = "common"
+    "#;
+
+    fn line(text: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&text.to_string()).unwrap()
+    }
+
+    #[test]
+    fn parse_campaign() {
+        let campaign = Campaign::parse(CAMPAIGN).unwrap();
+        assert_eq!(campaign.sites, vec!["sas", "pgr"]);
+        assert_eq!(
+            campaign.lines,
+            vec![
+                line("20250408 18:00 120 15 twofsound special --embargo"),
+                line("20250409 00:00 - 0 normalscan common"),
+            ]
+        );
+
+        let invalid = CAMPAIGN.replace("\"special\"", "\"sometimes\"");
+        assert!(matches!(
+            Campaign::parse(&invalid),
+            Err(CampaignError::Line { number: 1, .. })
+        ));
+        let unknown = CAMPAIGN.replace("priority = 0", "priority = 0\nprio = 1");
+        assert!(matches!(
+            Campaign::parse(&unknown),
+            Err(CampaignError::Toml(_))
+        ));
+        assert!(matches!(
+            Campaign::parse("sites = []"),
+            Err(CampaignError::NoSites)
+        ));
+        assert!(matches!(
+            Campaign::parse("sites = [\"sas\"]"),
+            Err(CampaignError::NoLines)
+        ));
+    }
+
+    #[test]
+    fn plan_site() {
+        let campaign = Campaign::parse(CAMPAIGN).unwrap();
+        let document = ScdDocument::parse(
+            "# baseline\n\
+            20200101 00:00 - 0 normalscan common\n\
+            20250408 19:00 60 15 full_fov special\n\
+            20250409 00:00 - 0 normalscan common\n",
+        );
+        let plan = campaign.plan(&document, parse_timestamp("20250101 00:00").unwrap());
+        assert_eq!(
+            plan.document.to_string(),
+            "# baseline\n\
+            20200101 00:00 - 0 normalscan common\n\
+            20250408 18:00 120 15 twofsound special --embargo\n\
+            20250408 19:00 60 15 full_fov special\n\
+            20250409 00:00 - 0 normalscan common\n"
+        );
+        assert_eq!(
+            plan.skipped,
+            vec![line("20250409 00:00 - 0 normalscan common")]
+        );
+        assert_eq!(plan.warnings.len(), 1);
+        assert_eq!(plan.warnings[0].0, campaign.lines[0]);
+    }
+}
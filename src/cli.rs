@@ -0,0 +1,771 @@
+//! Non-interactive subcommands, for modifying schedules from scripts.
+//!
+//! Results are written to stdout, one schedule line per line of output, and diagnostics are
+//! written to stderr. The process exit code tells the caller what happened.
+
+use crate::app::App;
+use crate::archive;
+use crate::backup::{self, Backup, DEFAULT_KEEP_BACKUPS};
+use crate::campaign::Campaign;
+use crate::lock::{LockError, ScheduleLock};
+use crate::schedule::conflict::{check_line, ScheduleWarning};
+use crate::schedule::diff::{diff, DiffLine};
+use crate::schedule::document::{LineDiagnostic, ScdDocument};
+use crate::schedule::recurrence::Recurrence;
+use crate::schedule::resolve::active_at;
+use crate::schedule::timezone::Zone;
+use crate::schedule::{parse_timestamp, ScheduleLine};
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+/// The schedule is invalid, a line could not be parsed, or warnings were raised in strict mode.
+const EXIT_INVALID: u8 = 1;
+/// The arguments are invalid, or a directory was neither given nor set in the environment.
+pub const EXIT_USAGE: u8 = 2;
+/// The schedule file could not be read or written.
+const EXIT_IO: u8 = 3;
+/// The requested line or backup does not exist, or nothing is scheduled at the requested time.
+const EXIT_NOT_FOUND: u8 = 4;
+/// Someone else is editing the schedule.
+const EXIT_LOCKED: u8 = 5;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print every line of the schedule, in file order
+    List(SiteArgs),
+
+    /// Add a line to the schedule
+    Add {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Schedule line to add, e.g. "20250101 00:00 120 10 normalscan special"
+        line: String,
+
+        /// Time zone the line's start time is in: "local" or "solar" time at the site, or a name
+        /// like "America/Regina" [default: UTC]
+        #[arg(long)]
+        zone: Option<String>,
+
+        /// Repeat the line following a rule, e.g. "weekly count=6" or "monthly until=20251231"
+        #[arg(long, value_name = "RULE")]
+        repeat: Option<String>,
+
+        /// Refuse to add the line if it raises any warnings
+        #[arg(long)]
+        strict: bool,
+
+        #[command(flatten)]
+        save: SaveArgs,
+    },
+
+    /// Remove a line from the schedule
+    Remove {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Schedule line to remove, exactly as `list` prints it
+        line: String,
+
+        #[command(flatten)]
+        save: SaveArgs,
+    },
+
+    /// Report every line of the schedule that does not parse, and conflicts between the rest
+    Validate {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Treat warnings as failures
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Print the line that will be running at a given time
+    Show {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Time to check, as "YYYYMMDD HH:MM" in UTC or "now"
+        #[arg(long, value_parser = parse_timestamp)]
+        at: DateTime<Utc>,
+    },
+
+    /// Move lines that will never run again into a dated archive file next to the schedule
+    Archive {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Archive lines that ended by this time, as "YYYYMMDD HH:MM" in UTC or "now"
+        #[arg(long, value_parser = parse_timestamp, default_value = "now")]
+        before: DateTime<Utc>,
+
+        /// Print the lines that would be archived without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        save: SaveArgs,
+    },
+
+    /// Add the lines of a campaign file to the schedules of all the sites it names, or none
+    Campaign {
+        /// Campaign file, in TOML
+        file: PathBuf,
+
+        /// Directory containing schedule files (overrides `LOCAL_SCHEDULE_DIR` from environment)
+        #[arg(short, long)]
+        schedule_dir: Option<PathBuf>,
+
+        /// Print the changes to each schedule without saving them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Refuse to apply the campaign if any line raises warnings on any site
+        #[arg(long)]
+        strict: bool,
+
+        #[command(flatten)]
+        save: SaveArgs,
+    },
+
+    /// List, compare and restore backups of the schedule
+    #[command(subcommand)]
+    Backup(BackupCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupCommand {
+    /// Print the backups of the schedule, most recent first
+    List(SiteArgs),
+
+    /// Print the changes that restoring a backup would make to the schedule
+    Diff {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Number of the backup, as printed by `backup list`
+        backup: usize,
+    },
+
+    /// Replace the schedule with a backup, after backing up the current schedule
+    Restore {
+        #[command(flatten)]
+        site: SiteArgs,
+
+        /// Number of the backup, as printed by `backup list`
+        backup: usize,
+
+        #[command(flatten)]
+        save: SaveArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct SiteArgs {
+    /// Three-letter site ID of radar to schedule
+    site_id: String,
+
+    /// Directory containing schedule files (overrides `LOCAL_SCHEDULE_DIR` from environment)
+    #[arg(short, long)]
+    schedule_dir: Option<PathBuf>,
+}
+impl SiteArgs {
+    /// Path to the schedule file of the site, reporting any error and converting it to an exit
+    /// code.
+    fn path(&self) -> Result<PathBuf, ExitCode> {
+        let dir = schedule_dir(self.schedule_dir.clone())?;
+        Ok(schedule_path(&self.site_id, &dir))
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct SaveArgs {
+    /// Number of backups of the schedule to keep when saving
+    #[arg(long, default_value_t = DEFAULT_KEEP_BACKUPS)]
+    keep_backups: usize,
+}
+
+/// The directory holding schedule files, falling back to `LOCAL_SCHEDULE_DIR` if no directory
+/// is given. If neither is, the error is reported and converted to an exit code.
+pub fn schedule_dir(schedule_dir: Option<PathBuf>) -> Result<PathBuf, ExitCode> {
+    match schedule_dir {
+        Some(x) => Ok(x),
+        None => match env::var_os("LOCAL_SCHEDULE_DIR") {
+            Some(x) => Ok(PathBuf::from(x)),
+            None => {
+                eprintln!("error: no schedule directory given, and LOCAL_SCHEDULE_DIR is not set");
+                Err(ExitCode::from(EXIT_USAGE))
+            }
+        },
+    }
+}
+
+/// Builds the path to the schedule file of `site_id` in `schedule_dir`.
+pub fn schedule_path(site_id: &str, schedule_dir: &Path) -> PathBuf {
+    let mut schedule_path = schedule_dir.join(site_id);
+    schedule_path.set_extension("scd");
+    schedule_path
+}
+
+/// Site IDs of every schedule file in `dir`, in alphabetical order.
+pub fn site_ids(dir: &Path) -> io::Result<Vec<String>> {
+    let mut sites = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|x| x == "scd") {
+            if let Some(stem) = path.file_stem() {
+                sites.push(stem.to_string_lossy().to_string());
+            }
+        }
+    }
+    sites.sort();
+    Ok(sites)
+}
+
+/// Runs a subcommand to completion, returning the exit code for the process.
+pub fn run(command: Command) -> ExitCode {
+    match command {
+        Command::List(site) => list(site),
+        Command::Add {
+            site,
+            line,
+            zone,
+            repeat,
+            strict,
+            save,
+        } => add(
+            site,
+            &line,
+            zone.as_deref(),
+            repeat.as_deref(),
+            strict,
+            save,
+        ),
+        Command::Remove { site, line, save } => remove(site, &line, save),
+        Command::Validate { site, strict } => validate(site, strict),
+        Command::Show { site, at } => show(site, at),
+        Command::Archive {
+            site,
+            before,
+            dry_run,
+            save,
+        } => archive(site, before, dry_run, save),
+        Command::Campaign {
+            file,
+            schedule_dir,
+            dry_run,
+            strict,
+            save,
+        } => campaign(&file, schedule_dir, dry_run, strict, save),
+        Command::Backup(BackupCommand::List(site)) => list_backups(site),
+        Command::Backup(BackupCommand::Diff { site, backup }) => diff_backup(site, backup),
+        Command::Backup(BackupCommand::Restore { site, backup, save }) => {
+            restore_backup(site, backup, save)
+        }
+    }
+}
+
+fn list(site: SiteArgs) -> ExitCode {
+    let document = match load(&site) {
+        Ok(document) => document,
+        Err(code) => return code,
+    };
+    for line in document.lines().iter() {
+        println!("{}", line.format());
+    }
+    ExitCode::SUCCESS
+}
+
+fn add(
+    site: SiteArgs,
+    line: &str,
+    zone: Option<&str>,
+    repeat: Option<&str>,
+    strict: bool,
+    save_args: SaveArgs,
+) -> ExitCode {
+    let _lock = match lock(&site) {
+        Ok(lock) => lock,
+        Err(code) => return code,
+    };
+    let mut document = match load(&site) {
+        Ok(document) => document,
+        Err(code) => return code,
+    };
+    let mut new_line = match ScheduleLine::try_from(&line.to_string()) {
+        Ok(x) => x,
+        Err(e) => {
+            eprint!("{}", e.report(line));
+            return ExitCode::from(EXIT_INVALID);
+        }
+    };
+    if let Some(zone) = zone {
+        let start = Zone::parse(zone, &site.site_id)
+            .and_then(|zone| zone.to_utc(new_line.timestamp.naive_utc()));
+        match start {
+            Ok(x) => new_line.timestamp = x,
+            Err(e) => return fail(EXIT_INVALID, &e),
+        }
+    }
+
+    let Some(rule) = repeat else {
+        let warnings = check_line(&new_line, &document.lines(), Utc::now());
+        for warning in warnings.iter() {
+            eprintln!("warning: {warning}");
+        }
+        if strict && !warnings.is_empty() {
+            eprintln!("error: not adding line with warnings in strict mode");
+            return ExitCode::from(EXIT_INVALID);
+        }
+
+        println!("{}", new_line.format());
+        document.insert(new_line);
+        return save(&site, &document, &save_args);
+    };
+
+    let new_lines = match Recurrence::parse(rule).and_then(|x| x.expand(&new_line)) {
+        Ok(x) => x,
+        Err(e) => return fail(EXIT_INVALID, &e),
+    };
+    if new_lines.is_empty() {
+        eprintln!("error: the rule {rule:?} repeats the line no times");
+        return ExitCode::from(EXIT_INVALID);
+    }
+    // Each line is checked against the ones added before it as well as the schedule
+    let now = Utc::now();
+    let mut warning_count = 0;
+    for line in new_lines.iter() {
+        for warning in check_line(line, &document.lines(), now) {
+            eprintln!("warning: {}: {warning}", line.format());
+            warning_count += 1;
+        }
+        document.insert(line.clone());
+    }
+    if strict && warning_count > 0 {
+        eprintln!("error: not adding lines with warnings in strict mode");
+        return ExitCode::from(EXIT_INVALID);
+    }
+
+    for line in new_lines.iter() {
+        println!("{}", line.format());
+    }
+    save(&site, &document, &save_args)
+}
+
+fn remove(site: SiteArgs, line: &str, save_args: SaveArgs) -> ExitCode {
+    let _lock = match lock(&site) {
+        Ok(lock) => lock,
+        Err(code) => return code,
+    };
+    let mut document = match load(&site) {
+        Ok(document) => document,
+        Err(code) => return code,
+    };
+    let old_line = match ScheduleLine::try_from(&line.to_string()) {
+        Ok(x) => x,
+        Err(e) => return fail(EXIT_INVALID, &e),
+    };
+    if !document.remove(&old_line) {
+        eprintln!("error: line not found in schedule: {}", old_line.format());
+        return ExitCode::from(EXIT_NOT_FOUND);
+    }
+
+    println!("{}", old_line.format());
+    save(&site, &document, &save_args)
+}
+
+fn validate(site: SiteArgs, strict: bool) -> ExitCode {
+    let path = match site.path() {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let document = match App::load_schedule_lenient(&path) {
+        Ok(document) => document,
+        Err(e) => return fail(EXIT_IO, e.as_ref()),
+    };
+    let diagnostics = document.diagnostics();
+    for diagnostic in diagnostics.iter() {
+        eprint!("{}", diagnostic.report());
+    }
+    let lines = document.lines();
+
+    // Lines in the past are expected in a schedule, so only conflicts are reported
+    let now = Utc::now();
+    let mut warning_count = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let mut others = lines.clone();
+        others.remove(i);
+        for warning in check_line(line, &others, now) {
+            if warning == ScheduleWarning::InPast {
+                continue;
+            }
+            eprintln!("warning: {}: {warning}", line.format());
+            warning_count += 1;
+        }
+    }
+
+    if !diagnostics.is_empty() || (strict && warning_count > 0) {
+        ExitCode::from(EXIT_INVALID)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn show(site: SiteArgs, at: DateTime<Utc>) -> ExitCode {
+    let lines = match load(&site) {
+        Ok(document) => document.lines(),
+        Err(code) => return code,
+    };
+    match active_at(&lines, at) {
+        Some(line) => {
+            println!("{}", line.format());
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("nothing scheduled at {}", at.format("%Y%m%d %H:%M"));
+            ExitCode::from(EXIT_NOT_FOUND)
+        }
+    }
+}
+
+fn archive(site: SiteArgs, before: DateTime<Utc>, dry_run: bool, save_args: SaveArgs) -> ExitCode {
+    let _lock = if dry_run {
+        None
+    } else {
+        match lock(&site) {
+            Ok(lock) => Some(lock),
+            Err(code) => return code,
+        }
+    };
+    let mut document = match load(&site) {
+        Ok(document) => document,
+        Err(code) => return code,
+    };
+    let ended = archive::ended_lines(&document.lines(), before);
+    for line in ended.iter() {
+        println!("{}", line.format());
+    }
+    if dry_run || ended.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    // Archive the lines before removing them, so that they are never lost
+    let path = match site.path() {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    match archive::append(&path, &ended, Utc::now().date_naive()) {
+        Ok(archive_path) => eprintln!(
+            "archived {} lines to {}",
+            ended.len(),
+            archive_path.display()
+        ),
+        Err(e) => return fail(EXIT_IO, &e),
+    }
+    for line in ended.iter() {
+        document.remove(line);
+    }
+    save(&site, &document, &save_args)
+}
+
+fn campaign(
+    file: &Path,
+    schedule_dir: Option<PathBuf>,
+    dry_run: bool,
+    strict: bool,
+    save_args: SaveArgs,
+) -> ExitCode {
+    let campaign = match fs::read_to_string(file) {
+        Ok(text) => match Campaign::parse(&text) {
+            Ok(x) => x,
+            Err(e) => return fail(EXIT_INVALID, &e),
+        },
+        Err(e) => return fail(EXIT_IO, &e),
+    };
+    let sites: Vec<SiteArgs> = campaign
+        .sites
+        .iter()
+        .map(|site_id| SiteArgs {
+            site_id: site_id.clone(),
+            schedule_dir: schedule_dir.clone(),
+        })
+        .collect();
+
+    // Every schedule is locked and loaded before any is changed, so that nothing is saved if
+    // one of them can't be
+    let mut locks = vec![];
+    if !dry_run {
+        for site in sites.iter() {
+            match lock(site) {
+                Ok(lock) => locks.push(lock),
+                Err(code) => return code,
+            }
+        }
+    }
+    let mut documents = vec![];
+    for site in sites.iter() {
+        match load(site) {
+            Ok(document) => documents.push(document),
+            Err(code) => return code,
+        }
+    }
+
+    let now = Utc::now();
+    let plans: Vec<_> = documents.iter().map(|x| campaign.plan(x, now)).collect();
+    let mut warning_count = 0;
+    for ((site, document), plan) in sites.iter().zip(documents.iter()).zip(plans.iter()) {
+        println!("{}:", site.site_id);
+        for line in diff(&document.to_string(), &plan.document.to_string()) {
+            if !matches!(line, DiffLine::Same(_)) {
+                println!("{line}");
+            }
+        }
+        for line in plan.skipped.iter() {
+            eprintln!("{}: already scheduled: {}", site.site_id, line.format());
+        }
+        for (line, warning) in plan.warnings.iter() {
+            eprintln!("warning: {}: {}: {warning}", site.site_id, line.format());
+            warning_count += 1;
+        }
+    }
+    if strict && warning_count > 0 {
+        eprintln!("error: not applying campaign with warnings in strict mode");
+        return ExitCode::from(EXIT_INVALID);
+    }
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    // If a schedule can't be saved, those already saved are put back as they were
+    let mut saved = vec![];
+    for (i, site) in sites.iter().enumerate() {
+        if plans[i].document == documents[i] {
+            continue;
+        }
+        match save_backed_up(site, &plans[i].document, &save_args) {
+            Ok(backup) => saved.push((site, backup, &documents[i])),
+            Err(code) => {
+                eprintln!("error: unable to save {}, rolling back", site.site_id);
+                for (site, backup, original) in saved.iter().rev() {
+                    roll_back(site, backup.as_deref(), original, &save_args);
+                }
+                return code;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Puts the schedule of `site` back as it was before a campaign was saved to it, from the
+/// `backup` made while saving, or from the `original` schedule if no backup was kept. The
+/// schedule with the campaign is backed up in turn, like any other save.
+fn roll_back(site: &SiteArgs, backup: Option<&Path>, original: &ScdDocument, save_args: &SaveArgs) {
+    let restored = match backup {
+        Some(path) => fs::read_to_string(path).map(|x| ScdDocument::parse(&x)),
+        None => Ok(original.clone()),
+    };
+    // One more backup than usual is kept, so that the one being restored isn't removed to make
+    // room for the backup of the campaign
+    let restore_args = SaveArgs {
+        keep_backups: match save_args.keep_backups {
+            0 => 0,
+            n => n + 1,
+        },
+    };
+    let code = match restored {
+        Ok(document) => save(site, &document, &restore_args),
+        Err(e) => fail(EXIT_IO, &e),
+    };
+    match (code == ExitCode::SUCCESS, backup) {
+        (true, _) => eprintln!("rolled back {}", site.site_id),
+        (false, Some(path)) => eprintln!(
+            "error: unable to roll back {}, restore it from {}",
+            site.site_id,
+            path.display()
+        ),
+        (false, None) => eprintln!(
+            "error: unable to roll back {}, and no backup was kept",
+            site.site_id
+        ),
+    }
+}
+
+fn list_backups(site: SiteArgs) -> ExitCode {
+    let path = match site.path() {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let backups = match backup::list(&path) {
+        Ok(x) => x,
+        Err(e) => return fail(EXIT_IO, &e),
+    };
+    for (i, backup) in backups.iter().enumerate() {
+        println!(
+            "{}  {}  {}",
+            i + 1,
+            backup.time.format("%Y%m%d %H:%M:%S"),
+            backup.name()
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn diff_backup(site: SiteArgs, number: usize) -> ExitCode {
+    let path = match site.path() {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let backup = match find_backup(&path, number) {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let (current, backup) = match (std::fs::read_to_string(&path), backup.read()) {
+        (Ok(current), Ok(backup)) => (current, backup),
+        (Err(e), _) | (_, Err(e)) => return fail(EXIT_IO, &e),
+    };
+    for line in diff(&current, &backup) {
+        if !matches!(line, DiffLine::Same(_)) {
+            println!("{line}");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn restore_backup(site: SiteArgs, number: usize, save_args: SaveArgs) -> ExitCode {
+    let _lock = match lock(&site) {
+        Ok(lock) => lock,
+        Err(code) => return code,
+    };
+    let path = match site.path() {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let backup = match find_backup(&path, number) {
+        Ok(x) => x,
+        Err(code) => return code,
+    };
+    let document = match backup.read() {
+        Ok(text) => ScdDocument::parse(&text),
+        Err(e) => return fail(EXIT_IO, &e),
+    };
+    println!("{}", backup.name());
+    save(&site, &document, &save_args)
+}
+
+/// Finds a backup by its number in `backup list`, reporting any error and converting it to an
+/// exit code.
+fn find_backup(path: &Path, number: usize) -> Result<Backup, ExitCode> {
+    let backups = backup::list(path).map_err(|e| fail(EXIT_IO, &e))?;
+    match number.checked_sub(1).and_then(|i| backups.get(i)) {
+        Some(backup) => Ok(backup.clone()),
+        None => {
+            eprintln!(
+                "error: no backup {number}, there are {} backups",
+                backups.len()
+            );
+            Err(ExitCode::from(EXIT_NOT_FOUND))
+        }
+    }
+}
+
+/// Locks the schedule of a site for the duration of a change, reporting any error and converting
+/// it to an exit code.
+fn lock(site: &SiteArgs) -> Result<ScheduleLock, ExitCode> {
+    let path = site.path()?;
+    let lock = ScheduleLock::acquire(&path, false).map_err(|e| match e {
+        LockError::Held { .. } => fail(EXIT_LOCKED, &e),
+        LockError::Io(_) => fail(EXIT_IO, &e),
+    })?;
+    if let Some(holder) = &lock.replaced {
+        eprintln!("warning: took over lock held by {holder}");
+    }
+    Ok(lock)
+}
+
+/// Loads the schedule of a site, reporting any error and converting it to an exit code.
+fn load(site: &SiteArgs) -> Result<ScdDocument, ExitCode> {
+    let path = site.path()?;
+    App::load_schedule(&path).map_err(|e| match e.downcast_ref::<LineDiagnostic>() {
+        Some(diagnostic) => {
+            eprint!("{}", diagnostic.report());
+            ExitCode::from(EXIT_INVALID)
+        }
+        None => fail(EXIT_IO, e.as_ref()),
+    })
+}
+
+/// Saves the schedule of a site, reporting any error and converting it to an exit code.
+fn save(site: &SiteArgs, document: &ScdDocument, save_args: &SaveArgs) -> ExitCode {
+    match save_backed_up(site, document, save_args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(code) => code,
+    }
+}
+
+/// Saves the schedule of a site like [`save`], returning the path of the backup made of it, if
+/// any.
+fn save_backed_up(
+    site: &SiteArgs,
+    document: &ScdDocument,
+    save_args: &SaveArgs,
+) -> Result<Option<PathBuf>, ExitCode> {
+    let path = site.path()?;
+    App::write_schedule(&path, document, save_args.keep_backups)
+        .map_err(|e| fail(EXIT_IO, e.as_ref()))
+}
+
+fn fail(code: u8, err: &dyn Error) -> ExitCode {
+    eprintln!("error: {err}");
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const SCHEDULE: &[u8] = b"# Operators: edit with care\n20200101 00:00 - 0 normalscan common\n";
+    const CAMPAIGN: &str = r#"
+        sites = ["sas", "pgr"]
+
+        [[line]]
+        start = "20300408 18:00"
+        duration = 120
+        priority = 15
+        experiment = "twofsound"
+        mode = "special"
+    "#;
+
+    #[test]
+    fn campaign_rolls_back_saved_sites() {
+        let dir = tempdir().unwrap();
+        let campaign_file = dir.path().join("campaign.toml");
+        fs::write(&campaign_file, CAMPAIGN).unwrap();
+        let sas = dir.path().join("sas.scd");
+        fs::write(&sas, SCHEDULE).unwrap();
+        fs::write(dir.path().join("pgr.scd"), SCHEDULE).unwrap();
+        // A directory in the way of the temporary file stops pgr from being saved
+        let temp_file = format!(".pgr.scd.{}.tmp", std::process::id());
+        fs::create_dir(dir.path().join(temp_file)).unwrap();
+
+        let save_args = SaveArgs { keep_backups: 1 };
+        let code = campaign(
+            &campaign_file,
+            Some(dir.path().into()),
+            false,
+            false,
+            save_args,
+        );
+        assert_eq!(code, ExitCode::from(EXIT_IO));
+        assert_eq!(fs::read(&sas).unwrap(), SCHEDULE);
+
+        // The schedule with the campaign is kept, in case rolling back was a mistake
+        let backups = backup::list(&sas).unwrap();
+        assert!(backups[0].read().unwrap().contains("20300408 18:00"));
+    }
+}
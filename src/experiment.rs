@@ -0,0 +1,693 @@
+//! Borealis experiments, and what can be learned about them from their source.
+//!
+//! Experiments are Python files, which are read with a small scanner rather than a Python
+//! interpreter. It picks out the docstrings, the CPID, the keyword arguments that `__init__`
+//! accepts or reads from `kwargs`, and the number of slices added, which covers how experiments
+//! are written in practice without having to run them.
+
+use crate::schedule::conflict::ScheduleWarning;
+use crate::schedule::{Field, FieldError, Kwargs, ScheduleError};
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Type of a keyword argument, as far as it can be told from the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KwargType {
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+impl KwargType {
+    fn from_name(name: &str) -> Option<KwargType> {
+        match name {
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "bool" => Some(Self::Bool),
+            "str" => Some(Self::Str),
+            _ => None,
+        }
+    }
+
+    /// Type of a Python literal, e.g. a default value.
+    fn of_literal(token: &Token) -> Option<KwargType> {
+        match token {
+            Token::Number(x) if x.contains(['.', 'e', 'E']) => Some(Self::Float),
+            Token::Number(_) => Some(Self::Int),
+            Token::Str(_) => Some(Self::Str),
+            Token::Name(x) if x == "True" || x == "False" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+
+    /// Whether `value` can be given for a kwarg of this type.
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            Self::Int => value.parse::<i64>().is_ok(),
+            Self::Float => value.parse::<f64>().is_ok(),
+            Self::Bool => ["true", "false", "1", "0"].contains(&value.to_lowercase().as_str()),
+            Self::Str => true,
+        }
+    }
+}
+impl Display for KwargType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Str => "str",
+        })
+    }
+}
+
+/// A keyword argument accepted by an experiment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kwarg {
+    pub name: String,
+    pub kind: Option<KwargType>,
+    pub description: Option<String>,
+}
+
+/// A Borealis experiment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Experiment {
+    /// Name used in schedule lines, i.e. the file name without extension.
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub modified: Option<DateTime<Utc>>,
+    /// Docstring of the experiment class, or of the module if the class has none.
+    pub description: Option<String>,
+    pub cpid: Option<i64>,
+    pub kwargs: Vec<Kwarg>,
+    /// Whether `__init__` takes `**kwargs`, so may accept kwargs that were not found.
+    pub var_kwargs: bool,
+    /// Number of `add_slice` calls.
+    pub slices: usize,
+}
+impl Experiment {
+    /// An experiment that is known only by name, e.g. one used in the schedule that is no longer
+    /// in the experiments directory.
+    pub fn unknown(name: &str) -> Experiment {
+        Experiment {
+            name: name.to_string(),
+            path: None,
+            modified: None,
+            description: None,
+            cpid: None,
+            kwargs: vec![],
+            var_kwargs: false,
+            slices: 0,
+        }
+    }
+
+    /// Reads an experiment from a Python file. Returns `None` for Python files that do not
+    /// define a class, such as modules of shared constants.
+    pub fn load(path: &Path) -> Result<Option<Experiment>, Box<dyn Error>> {
+        let source = std::fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let Some(mut experiment) = Self::parse(&name, &source) else {
+            return Ok(None);
+        };
+        experiment.path = Some(path.to_path_buf());
+        experiment.modified = std::fs::metadata(path)?.modified().ok().map(DateTime::from);
+        Ok(Some(experiment))
+    }
+
+    /// Scans the source of an experiment. Returns `None` if it does not define a class.
+    pub fn parse(name: &str, source: &str) -> Option<Experiment> {
+        let tokens = tokenize(source);
+        let at = |i: usize| tokens.get(i).unwrap_or(&Token::Op('\0'));
+
+        let module_doc = match tokens.first() {
+            Some(Token::Str(x)) => Some(clean_docstring(x)),
+            _ => None,
+        };
+        let mut class_doc = None;
+        let mut init_doc = None;
+        let mut found_class = false;
+        let mut cpid = None;
+        let mut slices = 0;
+        let mut var_kwargs = false;
+        let mut kwargs: Vec<Kwarg> = vec![];
+        let mut add_kwarg = |name: &str, kind: Option<KwargType>| match kwargs
+            .iter_mut()
+            .find(|x| x.name == name)
+        {
+            Some(kwarg) => {
+                kwarg.kind = kwarg.kind.or(kind);
+            }
+            None => kwargs.push(Kwarg {
+                name: name.to_string(),
+                kind,
+                description: None,
+            }),
+        };
+
+        for i in 0..tokens.len() {
+            match (&tokens[i], at(i + 1)) {
+                (Token::Name(x), Token::Name(_)) if x == "class" => {
+                    if !found_class {
+                        class_doc = docstring_after_colon(&tokens, i);
+                    }
+                    found_class = true;
+                }
+                (Token::Name(x), Token::Name(y)) if x == "def" && y == "__init__" => {
+                    init_doc = docstring_after_colon(&tokens, i);
+                    let (names, takes_kwargs) = parameters(&tokens, i + 2);
+                    for (name, kind) in names {
+                        add_kwarg(&name, kind);
+                    }
+                    var_kwargs |= takes_kwargs;
+                }
+                (Token::Name(x), Token::Op('=')) if x.eq_ignore_ascii_case("cpid") => {
+                    cpid = cpid.or(match (at(i + 2), at(i + 3)) {
+                        (Token::Number(n), _) => n.parse().ok(),
+                        (Token::Op('-'), Token::Number(n)) => n.parse::<i64>().ok().map(|n| -n),
+                        _ => None,
+                    });
+                }
+                (Token::Name(x), Token::Op('(')) if x == "add_slice" => slices += 1,
+                (Token::Name(x), _) if x == "kwargs" => {
+                    // kwargs['key'], kwargs.get('key') or 'key' in kwargs
+                    let key = match (at(i + 1), at(i + 2), at(i + 3), at(i + 4)) {
+                        (Token::Op('['), Token::Str(key), _, _) => Some(key),
+                        (Token::Op('.'), Token::Name(get), Token::Op('('), Token::Str(key))
+                            if get == "get" =>
+                        {
+                            Some(key)
+                        }
+                        _ => match (i.checked_sub(2).map(|j| &tokens[j]), i.checked_sub(1)) {
+                            (Some(Token::Str(key)), Some(j))
+                                if tokens[j] == Token::Name("in".to_string()) =>
+                            {
+                                Some(key)
+                            }
+                            _ => None,
+                        },
+                    };
+                    // A conversion such as int(kwargs['key']) gives the type
+                    let kind = match i.checked_sub(2).map(|j| (&tokens[j], &tokens[j + 1])) {
+                        Some((Token::Name(cast), Token::Op('('))) => KwargType::from_name(cast),
+                        _ => None,
+                    };
+                    if let Some(key) = key {
+                        add_kwarg(key, kind);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !found_class {
+            return None;
+        }
+
+        // Experiments often describe their kwargs in the __init__ docstring, as "name: type, ..."
+        if let Some(doc) = &init_doc {
+            for kwarg in kwargs.iter_mut() {
+                let Some(rest) = doc
+                    .lines()
+                    .find_map(|line| line.trim().strip_prefix(&kwarg.name)?.strip_prefix(':'))
+                else {
+                    continue;
+                };
+                let mut rest = rest.trim();
+                let first_word = rest.split([',', ' ']).next().unwrap_or_default();
+                if let Some(kind) = KwargType::from_name(first_word) {
+                    kwarg.kind = kwarg.kind.or(Some(kind));
+                    rest = rest[first_word.len()..].trim_start_matches([',', ' ']);
+                }
+                if !rest.is_empty() {
+                    kwarg.description = Some(rest.to_string());
+                }
+            }
+        }
+
+        Some(Experiment {
+            name: name.to_string(),
+            path: None,
+            modified: None,
+            description: class_doc.or(module_doc),
+            cpid,
+            kwargs,
+            var_kwargs,
+            slices,
+        })
+    }
+
+    /// Checks that the experiment accepts each of `kwargs`, given as `--key`, `key` or
+    /// `key=value`, and that values are of the right type. Experiments that were not read from a
+    /// file are not checked, since nothing is known about what they accept.
+    ///
+    /// Experiments that take `**kwargs`, or where no kwargs were found at all, may read kwargs
+    /// in ways the scanner does not follow, so unknown kwargs are returned as warnings for them.
+    pub fn check_kwargs(&self, kwargs: &Kwargs) -> Result<Vec<ScheduleWarning>, ScheduleError> {
+        let mut warnings = vec![];
+        if self.path.is_none() {
+            return Ok(warnings);
+        }
+        for given in kwargs.iter() {
+            let Some(kwarg) = self.kwargs.iter().find(|x| x.name == given.key) else {
+                if self.var_kwargs || self.kwargs.is_empty() {
+                    warnings.push(ScheduleWarning::UnknownKwarg(given.to_string()));
+                    continue;
+                }
+                let known: Vec<&str> = self.kwargs.iter().map(|x| x.name.as_str()).collect();
+                return Err(ScheduleError::InvalidKwargs(FieldError::new(
+                    Field::Kwargs,
+                    &given.to_string(),
+                    &format!("kwarg accepted by {} ({})", self.name, known.join(", ")),
+                )));
+            };
+            let Some(kind) = kwarg.kind else {
+                continue;
+            };
+            // A bare flag switches a boolean on
+            let valid = match &given.value {
+                Some(value) => kind.accepts(value),
+                None => kind == KwargType::Bool,
+            };
+            if !valid {
+                return Err(ScheduleError::InvalidKwargs(FieldError::new(
+                    Field::Kwargs,
+                    &given.to_string(),
+                    &format!("{}=<{kind}>", given.key),
+                )));
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// First line of the description, for showing in lists.
+    pub fn summary(&self) -> Option<&str> {
+        self.description
+            .as_deref()
+            .and_then(|x| x.lines().find(|line| !line.trim().is_empty()))
+    }
+}
+
+/// Loads every experiment in `dir`, sorted by name. Files that are not experiments are skipped.
+/// Files that cannot be read are kept by name, with the reason in place of a description.
+pub fn load_experiments<P>(dir: P) -> Result<Vec<Experiment>, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+{
+    let mut experiments = vec![];
+    for x in std::fs::read_dir(dir)?.flatten() {
+        let path = x.path();
+        let is_python = path.extension().is_some_and(|x| x == "py");
+        let is_private = x.file_name().to_string_lossy().starts_with('_');
+        // Follows symlinks, which are a common way of sharing experiments between directories
+        let is_file = std::fs::metadata(&path).is_ok_and(|x| x.is_file());
+        if !is_file || !is_python || is_private {
+            continue;
+        }
+        match Experiment::load(&path) {
+            Ok(Some(experiment)) => experiments.push(experiment),
+            Ok(None) => {}
+            Err(e) => {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                let mut experiment = Experiment::unknown(&name);
+                experiment.description = Some(format!("Unable to read the source: {e}"));
+                experiment.path = Some(path);
+                experiments.push(experiment);
+            }
+        }
+    }
+    experiments.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(experiments)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    /// Contents of a string literal, without quotes or escapes resolved.
+    Str(String),
+    Op(char),
+}
+
+/// Splits Python source into tokens, dropping comments and whitespace.
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_whitespace() || c == '\\' {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let (contents, end) = string_literal(&chars, i);
+            tokens.push(Token::Str(contents));
+            i = end;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            // String prefixes, as in r"..." or f'...'
+            let is_prefix = name.len() <= 2
+                && name.chars().all(|x| "rRbBfFuU".contains(x))
+                && matches!(chars.get(i), Some('"') | Some('\''));
+            if is_prefix {
+                let (contents, end) = string_literal(&chars, i);
+                tokens.push(Token::Str(contents));
+                i = end;
+            } else {
+                tokens.push(Token::Name(name));
+            }
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || "._".contains(chars[i])) {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Op(c));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Reads the string literal whose opening quote is at `start`, returning its contents and the
+/// index just past it.
+fn string_literal(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let delimiter = if triple { 3 } else { 1 };
+    let mut i = start + delimiter;
+    let contents_start = i;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        let closes = if triple {
+            chars[i..].starts_with(&[quote, quote, quote])
+        } else {
+            chars[i] == quote || chars[i] == '\n'
+        };
+        if closes {
+            let contents = chars[contents_start..i].iter().collect();
+            return (contents, i + delimiter);
+        }
+        i += 1;
+    }
+    (chars[contents_start.min(chars.len())..].iter().collect(), i)
+}
+
+/// The docstring of the `class` or `def` statement starting at token `start`, if it has one.
+fn docstring_after_colon(tokens: &[Token], start: usize) -> Option<String> {
+    let mut depth = 0;
+    for i in start..tokens.len() {
+        match &tokens[i] {
+            Token::Op('(') | Token::Op('[') => depth += 1,
+            Token::Op(')') | Token::Op(']') => depth -= 1,
+            Token::Op(':') if depth == 0 => {
+                return match tokens.get(i + 1) {
+                    Some(Token::Str(x)) => Some(clean_docstring(x)),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Named parameters of the function whose parameter list opens at token `start`, other than
+/// `self`, `*args` and `**kwargs`, along with their types if they have an annotation or default.
+/// Also returns whether the function takes `**kwargs`.
+fn parameters(tokens: &[Token], start: usize) -> (Vec<(String, Option<KwargType>)>, bool) {
+    let mut parameters = vec![];
+    let mut var_kwargs = false;
+    if tokens.get(start) != Some(&Token::Op('(')) {
+        return (parameters, var_kwargs);
+    }
+    let mut depth = 0;
+    // Whether the next name starts a parameter, and how many *s it follows
+    let mut expecting_name = true;
+    let mut stars = 0;
+    for i in start..tokens.len() {
+        match &tokens[i] {
+            Token::Op('(') | Token::Op('[') | Token::Op('{') => depth += 1,
+            Token::Op(')') | Token::Op(']') | Token::Op('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Op(',') if depth == 1 => {
+                expecting_name = true;
+                stars = 0;
+            }
+            Token::Op('*') if depth == 1 && expecting_name => stars += 1,
+            Token::Name(name) if depth == 1 && expecting_name => {
+                expecting_name = false;
+                var_kwargs |= stars == 2;
+                if stars > 0 || name == "self" {
+                    continue;
+                }
+                let kind = match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(Token::Op(':')), Some(Token::Name(x))) => KwargType::from_name(x),
+                    (Some(Token::Op('=')), Some(literal)) => KwargType::of_literal(literal),
+                    _ => None,
+                };
+                parameters.push((name.clone(), kind));
+            }
+            _ => {}
+        }
+    }
+    (parameters, var_kwargs)
+}
+
+/// Removes the indentation and surrounding blank lines of a docstring.
+fn clean_docstring(doc: &str) -> String {
+    let lines: Vec<&str> = doc.lines().collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| x.len() - x.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            if i == 0 {
+                x.trim()
+            } else {
+                x.get(indent..).unwrap_or("").trim_end()
+            }
+        })
+        .collect();
+    while cleaned.first().is_some_and(|x| x.is_empty()) {
+        cleaned.remove(0);
+    }
+    while cleaned.last().is_some_and(|x| x.is_empty()) {
+        cleaned.pop();
+    }
+    cleaned.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWOFSOUND: &str = r#"#!/usr/bin/python3
+
+"""
+    twofsound
+    ~~~~~~~~~
+    Sounding on two frequencies.
+
+    :copyright: 2019 SuperDARN Canada
+"""
+
+import copy
+from experiment_prototype.experiment_prototype import ExperimentPrototype
+import borealis_experiments.superdarn_common_fields as scf
+
+
+class Twofsound(ExperimentPrototype):
+    """
+    Alternates between two frequencies on every
+    integration, for comparing ionospheric conditions.
+    """
+
+    def __init__(self, embargo: bool = False, **kwargs):
+        """
+        kwargs:
+
+        freq1: int, first frequency to use [kHz]
+        freq2: int, second frequency to use [kHz]
+        """
+        cpid = 3503
+        freqs = (scf.COMMON_MODE_FREQ_1, scf.COMMON_MODE_FREQ_2)
+        if 'freq1' in kwargs:
+            freqs = (int(kwargs['freq1']), freqs[1])
+        freqs = (freqs[0], kwargs.get("freq2", freqs[1]))
+        # self.add_slice(...) in a comment is not a slice
+        super().__init__(cpid, comment_string="Twofsound classic scan")
+        self.add_slice({"freq": freqs[0]})
+        self.add_slice({"freq": freqs[1]}, interfacing_dict={0: 'SCAN'})
+"#;
+
+    #[test]
+    fn parse_experiment() {
+        let experiment = Experiment::parse("twofsound", TWOFSOUND).unwrap();
+        assert_eq!(experiment.name, "twofsound");
+        assert_eq!(
+            experiment.description.as_deref(),
+            Some(
+                "Alternates between two frequencies on every\n\
+                integration, for comparing ionospheric conditions."
+            )
+        );
+        assert_eq!(
+            experiment.summary(),
+            Some("Alternates between two frequencies on every")
+        );
+        assert_eq!(experiment.cpid, Some(3503));
+        assert_eq!(experiment.slices, 2);
+        assert_eq!(
+            experiment.kwargs,
+            vec![
+                Kwarg {
+                    name: "embargo".to_string(),
+                    kind: Some(KwargType::Bool),
+                    description: None,
+                },
+                Kwarg {
+                    name: "freq1".to_string(),
+                    kind: Some(KwargType::Int),
+                    description: Some("first frequency to use [kHz]".to_string()),
+                },
+                Kwarg {
+                    name: "freq2".to_string(),
+                    kind: Some(KwargType::Int),
+                    description: Some("second frequency to use [kHz]".to_string()),
+                },
+            ]
+        );
+        assert!(experiment.var_kwargs);
+    }
+
+    #[test]
+    fn module_docstring_and_non_experiments() {
+        let source = "'''Common fields.'''\nclass Normalscan(ExperimentPrototype):\n    \
+            def __init__(self, *args):\n        super().__init__(cpid=-151)\n";
+        let experiment = Experiment::parse("normalscan", source).unwrap();
+        assert_eq!(experiment.description.as_deref(), Some("Common fields."));
+        assert_eq!(experiment.cpid, Some(-151));
+        assert!(experiment.kwargs.is_empty());
+        assert!(!experiment.var_kwargs);
+
+        assert_eq!(
+            Experiment::parse("superdarn_common_fields", "IS_FORWARD_RADAR = True\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn load_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &[u8]| std::fs::write(dir.path().join(name), contents);
+        write("twofsound.py", TWOFSOUND.as_bytes()).unwrap();
+        write("_private.py", TWOFSOUND.as_bytes()).unwrap();
+        write("constants.py", b"IS_FORWARD_RADAR = True\n").unwrap();
+        write("notes.txt", b"class Notes:\n").unwrap();
+        write("latin1.py", b"class Latin1:\n    '''Caf\xe9'''\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(
+            dir.path().join("twofsound.py"),
+            dir.path().join("linked.py"),
+        )
+        .unwrap();
+
+        let experiments = load_experiments(dir.path()).unwrap();
+        let names: Vec<&str> = experiments.iter().map(|x| x.name.as_str()).collect();
+        #[cfg(unix)]
+        assert_eq!(names, vec!["latin1", "linked", "twofsound"]);
+        #[cfg(not(unix))]
+        assert_eq!(names, vec!["latin1", "twofsound"]);
+
+        // One file that can't be read doesn't stop the others from loading
+        let latin1 = &experiments[0];
+        assert_eq!(latin1.path, Some(dir.path().join("latin1.py")));
+        assert!(latin1
+            .description
+            .as_deref()
+            .is_some_and(|x| x.starts_with("Unable to read the source: ")));
+        assert_eq!(experiments.last().unwrap().cpid, Some(3503));
+    }
+
+    #[test]
+    fn check_kwargs() {
+        let mut experiment = Experiment::parse("twofsound", TWOFSOUND).unwrap();
+        let check = |experiment: &Experiment, kwargs: &[&str]| {
+            let kwargs = Kwargs::parse(&kwargs.join(" ")).unwrap();
+            experiment.check_kwargs(&kwargs).map_err(|e| e.to_string())
+        };
+        assert_eq!(check(&experiment, &[]), Ok(vec![]));
+        assert_eq!(
+            check(&experiment, &["--embargo", "freq1=10500"]),
+            Ok(vec![])
+        );
+        assert_eq!(check(&experiment, &["embargo=false"]), Ok(vec![]));
+
+        // Experiments that are only known by name are not checked
+        assert_eq!(check(&experiment, &["--embrago"]), Ok(vec![]));
+        experiment.path = Some(PathBuf::from("twofsound.py"));
+
+        // __init__ takes **kwargs, so it may read kwargs that were not found
+        assert_eq!(
+            check(&experiment, &["--embrago", "freq1=10500"]),
+            Ok(vec![ScheduleWarning::UnknownKwarg("--embrago".to_string())])
+        );
+
+        experiment.var_kwargs = false;
+        assert_eq!(
+            check(&experiment, &["--embrago"]),
+            Err(
+                "Expected kwarg accepted by twofsound (embargo, freq1, freq2), got --embrago"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(&experiment, &["freq2=13.5"]),
+            Err("Expected freq2=<int>, got freq2=13.5".to_string())
+        );
+        assert_eq!(
+            check(&experiment, &["--freq1"]),
+            Err("Expected freq1=<int>, got --freq1".to_string())
+        );
+
+        let err = experiment
+            .check_kwargs(&Kwargs::parse("--embrago").unwrap())
+            .unwrap_err();
+        assert_eq!(err.field(), Field::Kwargs);
+
+        // Nothing is known about what an experiment without any kwargs found reads
+        experiment.kwargs.clear();
+        assert_eq!(
+            check(&experiment, &["speed=fast"]),
+            Ok(vec![ScheduleWarning::UnknownKwarg(
+                "speed=fast".to_string()
+            )])
+        );
+    }
+}
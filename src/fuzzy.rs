@@ -0,0 +1,92 @@
+//! Fuzzy matching for narrowing down lists as the user types.
+
+/// Bonus for a character matched straight after the previous one.
+const CONSECUTIVE_BONUS: i64 = 8;
+/// Bonus for a character matched at the start of a word, e.g. the `f` of `full_fov`.
+const WORD_START_BONUS: i64 = 8;
+/// Penalty per this many characters skipped before the first match.
+const LEADING_GAP: i64 = 4;
+
+/// Matches `pattern` against `text` if the characters of `pattern` appear in `text` in order,
+/// ignoring case. Returns a score, higher for better matches, and the indices of the matched
+/// characters of `text`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<String> = pattern
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .map(|x| x.to_lowercase().collect())
+        .collect();
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<String> = text.iter().map(|x| x.to_lowercase().collect()).collect();
+    let is_word_start = |i: usize| i == 0 || !text[i - 1].is_alphanumeric();
+
+    // best[j][i] is the best score for matching pattern[..=j] with pattern[j] at text[i], along
+    // with where pattern[j - 1] was matched
+    let mut best: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; text.len()]; pattern.len()];
+    for (j, p) in pattern.iter().enumerate() {
+        for i in (0..text.len()).filter(|&i| lower[i] == *p) {
+            let bonus = 1 + if is_word_start(i) {
+                WORD_START_BONUS
+            } else {
+                0
+            };
+            best[j][i] = if j == 0 {
+                Some((bonus - i as i64 / LEADING_GAP, 0))
+            } else {
+                (0..i)
+                    .filter_map(|k| {
+                        let (score, _) = best[j - 1][k]?;
+                        let step = if k + 1 == i {
+                            CONSECUTIVE_BONUS
+                        } else {
+                            -((i - k) as i64)
+                        };
+                        Some((score + step + bonus, k))
+                    })
+                    .max_by_key(|(score, _)| *score)
+            };
+        }
+    }
+
+    let Some(last) = pattern.len().checked_sub(1) else {
+        return Some((0, vec![]));
+    };
+    let (mut i, (score, _)) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| Some((i, (*x)?)))
+        .max_by_key(|(_, (score, _))| *score)?;
+    let mut indices = vec![i];
+    for j in (1..=last).rev() {
+        i = best[j][i]?.1;
+        indices.push(i);
+    }
+    indices.reverse();
+    Some((score, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_in_order() {
+        assert_eq!(fuzzy_match("", "normalscan"), Some((0, vec![])));
+        assert_eq!(fuzzy_match("nsc", "normalscan").unwrap().1, vec![0, 6, 7]);
+        assert_eq!(fuzzy_match("FOV", "full_fov").unwrap().1, vec![5, 6, 7]);
+        assert_eq!(fuzzy_match("scn", "normalscan").unwrap().1, vec![6, 7, 9]);
+        assert_eq!(fuzzy_match("fn", "normalscan"), None);
+        assert_eq!(
+            fuzzy_match("twof", "twofsound").unwrap().1,
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn better_matches_score_higher() {
+        let score = |pattern, text| fuzzy_match(pattern, text).unwrap().0;
+        assert!(score("twof", "twofsound") > score("twof", "two_freq_sound"));
+        assert!(score("fov", "full_fov") > score("fov", "fixed_overlap_scan"));
+        assert!(score("norm", "normalscan") > score("norm", "interleaved_normalscan"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Advisory locking of schedule files, so that two people cannot edit the same schedule at once.
+//!
+//! A lock is a file next to the schedule, e.g. `sas.scd.lock`, holding the user, host and process
+//! that is editing the schedule. The lock file is removed when the lock is dropped, which also
+//! happens while unwinding from a panic.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, process};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("{} is being edited by {holder} (remove {} if this is not the case)", .schedule.display(), .lock_file.display())]
+    Held {
+        schedule: PathBuf,
+        lock_file: PathBuf,
+        holder: LockHolder,
+    },
+
+    #[error("Unable to lock schedule: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Who is holding a lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockHolder {
+    pub user: String,
+    pub host: String,
+    pub pid: u32,
+    pub since: DateTime<Utc>,
+}
+impl LockHolder {
+    /// Describes the current process.
+    fn current() -> LockHolder {
+        LockHolder {
+            user: env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or("unknown".to_string()),
+            host: hostname(),
+            pid: process::id(),
+            since: Utc::now(),
+        }
+    }
+
+    /// Reads the contents of a lock file, as written by `LockHolder::contents`.
+    fn parse(contents: &str) -> Option<LockHolder> {
+        let mut user = None;
+        let mut host = None;
+        let mut pid = None;
+        let mut since = None;
+        for line in contents.lines() {
+            match line.split_once('=') {
+                Some(("user", x)) => user = Some(x.to_string()),
+                Some(("host", x)) => host = Some(x.to_string()),
+                Some(("pid", x)) => pid = x.parse().ok(),
+                Some(("since", x)) => {
+                    since = NaiveDateTime::parse_from_str(x, "%Y%m%d %H:%M")
+                        .ok()
+                        .map(|x| x.and_utc())
+                }
+                _ => {}
+            }
+        }
+        Some(LockHolder {
+            user: user?,
+            host: host?,
+            pid: pid?,
+            since: since?,
+        })
+    }
+
+    fn contents(&self) -> String {
+        format!(
+            "user={}\nhost={}\npid={}\nsince={}\n",
+            self.user,
+            self.host,
+            self.pid,
+            self.since.format("%Y%m%d %H:%M")
+        )
+    }
+
+    /// Whether the process holding the lock is known to have exited. This can only be known for
+    /// processes on the same host.
+    fn is_stale(&self) -> bool {
+        let proc = Path::new("/proc");
+        self.host == hostname() && proc.is_dir() && !proc.join(self.pid.to_string()).exists()
+    }
+}
+impl Display for LockHolder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}@{} (pid {}) since {}",
+            self.user,
+            self.host,
+            self.pid,
+            self.since.format("%Y%m%d %H:%M")
+        )
+    }
+}
+
+/// A held lock on a schedule file, which is released when dropped.
+#[derive(Debug)]
+pub struct ScheduleLock {
+    lock_file: PathBuf,
+    holder: LockHolder,
+    /// The holder of a lock that was taken over when acquiring this one.
+    pub replaced: Option<LockHolder>,
+}
+impl ScheduleLock {
+    /// Locks the schedule at `schedule`. A lock left behind by a process that has since exited
+    /// is taken over, as is any existing lock if `force` is set; otherwise an existing lock is an
+    /// error.
+    pub fn acquire(schedule: &Path, force: bool) -> Result<ScheduleLock, LockError> {
+        let lock_file = lock_path(schedule);
+        let holder = LockHolder::current();
+        let mut replaced = None;
+        loop {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_file)
+            {
+                Ok(mut file) => {
+                    file.write_all(holder.contents().as_bytes())?;
+                    return Ok(ScheduleLock {
+                        lock_file,
+                        holder,
+                        replaced,
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && replaced.is_none() => {
+                    let existing =
+                        LockHolder::parse(&fs::read_to_string(&lock_file)?).unwrap_or(LockHolder {
+                            user: "unknown".to_string(),
+                            host: "unknown".to_string(),
+                            pid: 0,
+                            since: DateTime::UNIX_EPOCH,
+                        });
+                    if !force && !existing.is_stale() {
+                        return Err(LockError::Held {
+                            schedule: schedule.to_path_buf(),
+                            lock_file,
+                            holder: existing,
+                        });
+                    }
+                    fs::remove_file(&lock_file)?;
+                    replaced = Some(existing);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+impl Drop for ScheduleLock {
+    fn drop(&mut self) {
+        // Leave the lock alone if someone else has taken it over in the meantime
+        let ours = fs::read_to_string(&self.lock_file)
+            .ok()
+            .and_then(|x| LockHolder::parse(&x))
+            .is_some_and(|x| x.host == self.holder.host && x.pid == self.holder.pid);
+        if ours {
+            let _ = fs::remove_file(&self.lock_file);
+        }
+    }
+}
+
+/// Path of the lock file for the schedule at `schedule`.
+fn lock_path(schedule: &Path) -> PathBuf {
+    let mut name = schedule.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    schedule.with_file_name(name)
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/etc/hostname")
+        .map(|x| x.trim().to_string())
+        .ok()
+        .filter(|x| !x.is_empty())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .unwrap_or("unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let dir = tempdir().unwrap();
+        let schedule = dir.path().join("sas.scd");
+        let lock = ScheduleLock::acquire(&schedule, false).unwrap();
+        assert!(lock.replaced.is_none());
+        assert!(lock_path(&schedule).exists());
+        assert!(matches!(
+            ScheduleLock::acquire(&schedule, false),
+            Err(LockError::Held { .. })
+        ));
+        drop(lock);
+        assert!(!lock_path(&schedule).exists());
+        assert!(ScheduleLock::acquire(&schedule, false).is_ok());
+    }
+
+    #[test]
+    fn stale_and_replaced_locks() {
+        let dir = tempdir().unwrap();
+        let schedule = dir.path().join("sas.scd");
+        let mut other = LockHolder::current();
+        other.pid = u32::MAX;
+        other.since = DateTime::UNIX_EPOCH;
+
+        // A lock from a process that no longer exists is taken over
+        fs::write(lock_path(&schedule), other.contents()).unwrap();
+        let lock = ScheduleLock::acquire(&schedule, false).unwrap();
+        assert_eq!(lock.replaced, Some(other.clone()));
+
+        // A lock that has been taken over by someone else is left for them to release
+        fs::write(lock_path(&schedule), other.contents()).unwrap();
+        drop(lock);
+        assert!(lock_path(&schedule).exists());
+    }
+}
@@ -1,14 +1,24 @@
 mod app;
+mod archive;
+mod backup;
+mod campaign;
+mod cli;
+mod experiment;
+mod fuzzy;
+mod lock;
 mod schedule;
+mod session;
 mod ui;
 
 use crate::app::{App, CurrentScreen, CurrentlyEditing};
-use crate::schedule::ScheduleError;
+use crate::backup::DEFAULT_KEEP_BACKUPS;
+use crate::cli::{schedule_dir, schedule_path, site_ids, Command, EXIT_USAGE};
+use crate::session::Session;
 use crate::ui::ui;
 use clap::Parser;
 use ratatui::backend::{Backend, CrosstermBackend};
 use ratatui::crossterm::event::{
-    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind,
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
 };
 use ratatui::crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -17,14 +27,19 @@ use ratatui::crossterm::{event, execute};
 use ratatui::Terminal;
 use std::error::Error;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::{env, io};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct ModifierArgs {
-    /// Three-letter site ID of radar to schedule
-    #[arg()]
-    site_id: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Three-letter site IDs of radars to schedule, separated by commas, e.g. `sas,pgr`
+    #[arg(required_unless_present = "all")]
+    site_id: Option<String>,
 
     /// Directory containing schedule files (overrides `LOCAL_SCHEDULE_DIR` from environment)
     #[arg()]
@@ -33,27 +48,110 @@ struct ModifierArgs {
     /// Path to borealis experiments directory (defaults to `$BOREALISPATH/src/borealis_experiments`)
     #[arg()]
     experiments_dir: Option<PathBuf>,
+
+    /// Directory containing schedule files, as an option rather than SCHEDULE_DIR
+    #[arg(
+        short = 's',
+        long = "schedule-dir",
+        value_name = "DIR",
+        conflicts_with = "schedule_dir"
+    )]
+    schedule_dir_option: Option<PathBuf>,
+
+    /// Path to borealis experiments directory, as an option rather than EXPERIMENTS_DIR
+    #[arg(
+        short = 'e',
+        long = "experiments-dir",
+        value_name = "DIR",
+        conflicts_with = "experiments_dir"
+    )]
+    experiments_dir_option: Option<PathBuf>,
+
+    /// Edit the schedules of every site in the schedule directory instead of SITE_ID, with the
+    /// directories given as options
+    #[arg(long, conflicts_with = "site_id")]
+    all: bool,
+
+    /// Take over the lock on the schedule, even if someone else appears to be editing it
+    #[arg(long)]
+    force_lock: bool,
+
+    /// Number of backups of the schedule to keep when saving
+    #[arg(long, default_value_t = DEFAULT_KEEP_BACKUPS)]
+    keep_backups: usize,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     let cli = ModifierArgs::parse();
-    let mut schedule_path = if let Some(x) = cli.schedule_dir {
-        x
-    } else {
-        PathBuf::from(env::var("LOCAL_SCHEDULE_DIR").expect("LOCAL_SCHEDULE_DIR unset"))
+    if let Some(command) = cli.command {
+        return Ok(cli::run(command));
+    }
+    let schedule_dir = match schedule_dir(cli.schedule_dir.or(cli.schedule_dir_option)) {
+        Ok(x) => x,
+        Err(code) => return Ok(code),
+    };
+    // Without site IDs, which can only be left out for --all, every site is edited
+    let sites = match cli.site_id {
+        Some(site_ids) => {
+            let mut sites: Vec<String> = vec![];
+            for site in site_ids.split(',') {
+                if !site.is_empty() && !sites.iter().any(|x| x == site) {
+                    sites.push(site.to_string());
+                }
+            }
+            if sites.is_empty() {
+                eprintln!("error: no site IDs given");
+                return Ok(ExitCode::from(EXIT_USAGE));
+            }
+            sites
+        }
+        None => match site_ids(&schedule_dir) {
+            Ok(sites) if !sites.is_empty() => sites,
+            Ok(_) => {
+                eprintln!("error: no schedules found in {}", schedule_dir.display());
+                return Ok(ExitCode::FAILURE);
+            }
+            Err(e) => {
+                eprintln!("error: unable to read {}: {e}", schedule_dir.display());
+                return Ok(ExitCode::FAILURE);
+            }
+        },
     };
-    schedule_path.push(cli.site_id);
-    schedule_path.set_extension("scd");
 
-    let experiments_path = if let Some(x) = cli.experiments_dir {
-        x
-    } else {
-        let mut temp = PathBuf::from(env::var("BOREALISPATH").expect("BOREALISPATH unset"));
-        temp.push("src");
-        temp.push("borealis_experiments");
-        temp
+    let experiments_path = match cli.experiments_dir.or(cli.experiments_dir_option) {
+        Some(x) => x,
+        None => match env::var_os("BOREALISPATH") {
+            Some(x) => PathBuf::from(x).join("src").join("borealis_experiments"),
+            None => {
+                eprintln!("error: no experiments directory given, and BOREALISPATH is not set");
+                return Ok(ExitCode::from(EXIT_USAGE));
+            }
+        },
     };
 
+    // Every schedule is locked before any editing starts, and the locks taken so far are
+    // released if one of them can't be
+    let mut apps = vec![];
+    for site in sites.iter() {
+        let app = match App::new(
+            schedule_path(site, &schedule_dir),
+            experiments_path.clone(),
+            cli.force_lock,
+            cli.keep_backups,
+        ) {
+            Ok(app) => app,
+            Err(e) => {
+                eprintln!("error: {site}: {e}");
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        if let Some(holder) = &app.lock.replaced {
+            eprintln!("warning: took over lock held by {holder}");
+        }
+        apps.push(app);
+    }
+    let mut session = Session::new(apps);
+
     // setup terminal
     enable_raw_mode()?;
     let mut stderr = io::stderr(); // This is a special case. Normally using stdout is fine.
@@ -62,13 +160,13 @@ fn main() -> Result<(), Box<dyn Error>> {
     let backend = CrosstermBackend::new(stderr);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let mut app = App::new(schedule_path, experiments_path);
-    let res = run_app(&mut terminal, &mut app);
+    // run app
+    let res = run_app(&mut terminal, &mut session);
 
-    if let Ok(true) = res {
-        app.save_schedule().unwrap();
-    }
+    let failures = match res {
+        Ok(true) => session.save(),
+        _ => vec![],
+    };
 
     // restore terminal
     disable_raw_mode()?;
@@ -82,44 +180,269 @@ fn main() -> Result<(), Box<dyn Error>> {
     if let Err(err) = res {
         println!("{err:?}");
     }
+    for (site, e) in failures.iter() {
+        eprintln!("error: unable to save the schedule of {site}: {e}");
+    }
 
-    Ok(())
+    Ok(if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<bool> {
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, session: &mut Session) -> io::Result<bool> {
     loop {
-        terminal.draw(|f| ui(f, app))?;
+        terminal.draw(|f| ui(f, session))?;
 
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Release {
                 // Skip events that are not KeyEventKind::Press
                 continue;
             }
+            let sites = session.apps.len();
+            let app = session.app_mut();
             match app.current_screen {
                 CurrentScreen::Main => match key.code {
+                    KeyCode::Tab if sites > 1 => {
+                        session.next_site();
+                    }
+                    KeyCode::BackTab if sites > 1 => {
+                        session.previous_site();
+                    }
+                    KeyCode::Char('m') if sites > 1 => {
+                        session.toggle_mark();
+                    }
+                    KeyCode::Char('c') if sites > 1 => {
+                        session.copy_last_change();
+                    }
                     KeyCode::Char('a') => {
                         app.current_screen = CurrentScreen::Adding;
-                        app.currently_editing = Some(CurrentlyEditing::Year);
+                        app.currently_editing = Some(CurrentlyEditing::Start);
                     }
-                    KeyCode::Char('r') => {
+                    KeyCode::Char('r') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
                         app.current_screen = CurrentScreen::Removing;
                         app.currently_editing = None;
                     }
+                    KeyCode::Char('e') => {
+                        app.current_screen = CurrentScreen::Editing;
+                        app.currently_editing = None;
+                    }
+                    KeyCode::Char('t') => {
+                        app.current_screen = CurrentScreen::Timeline;
+                    }
+                    KeyCode::Char('b') => {
+                        app.open_backups();
+                        app.current_screen = CurrentScreen::Backups;
+                    }
+                    KeyCode::Char('d') if !app.diagnostics.items.is_empty() => {
+                        app.current_screen = CurrentScreen::Diagnostics;
+                        if app.diagnostics.state.selected().is_none() {
+                            app.diagnostics.first();
+                        }
+                    }
+                    KeyCode::Char('u') => {
+                        app.undo();
+                    }
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.redo();
+                    }
+                    KeyCode::Char('p') => {
+                        app.preview_archive();
+                        app.current_screen = CurrentScreen::Archiving;
+                    }
+                    KeyCode::Char('/') => {
+                        app.current_screen = CurrentScreen::Searching;
+                    }
+                    KeyCode::Char('n') if app.search.is_some() => {
+                        app.move_selection(1, true);
+                    }
+                    KeyCode::Char('N') if app.search.is_some() => {
+                        app.move_selection(-1, true);
+                    }
+                    KeyCode::Esc => {
+                        app.clear_search();
+                    }
                     KeyCode::Char('q') => {
                         app.current_screen = CurrentScreen::Exiting;
                     }
                     _ => {}
                 },
-                CurrentScreen::Exiting => match key.code {
-                    KeyCode::Char('y') => {
-                        return Ok(true);
+                CurrentScreen::Searching => match key.code {
+                    KeyCode::Char(value) => {
+                        app.search_input.push(value);
+                        app.search_error = None;
+                    }
+                    KeyCode::Backspace => {
+                        app.search_input.pop();
+                        app.search_error = None;
+                    }
+                    KeyCode::Enter if app.search_input.trim().is_empty() => {
+                        app.clear_search();
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Enter if app.apply_search().is_ok() => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Esc => {
+                        app.search_input =
+                            app.search.as_ref().map_or(String::new(), |x| x.to_string());
+                        app.search_error = None;
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Timeline => match key.code {
+                    KeyCode::Char('q') => {
+                        app.current_screen = CurrentScreen::Exiting;
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Left => {
+                        app.pan_timeline(-1);
+                    }
+                    KeyCode::Right => {
+                        app.pan_timeline(1);
+                    }
+                    KeyCode::PageUp => {
+                        app.pan_timeline(-app.timeline_days);
+                    }
+                    KeyCode::PageDown => {
+                        app.pan_timeline(app.timeline_days);
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        app.zoom_timeline(-1);
+                    }
+                    KeyCode::Char('-') => {
+                        app.zoom_timeline(1);
+                    }
+                    KeyCode::Char('g') => {
+                        app.reset_timeline();
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Diagnostics if app.fix_input.is_some() => match key.code {
+                    KeyCode::Enter => {
+                        app.save_fix();
+                        if app.diagnostics.items.is_empty() {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app.fix_input = None;
+                        app.last_err = None;
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(input) = app.fix_input.as_mut() {
+                            input.pop();
+                        }
+                    }
+                    KeyCode::Char(value) => {
+                        if let Some(input) = app.fix_input.as_mut() {
+                            input.push(value);
+                        }
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Diagnostics => match key.code {
+                    KeyCode::Char('q') => {
+                        app.current_screen = CurrentScreen::Exiting;
+                    }
+                    KeyCode::Enter => {
+                        app.start_fix();
+                    }
+                    KeyCode::Char('x') | KeyCode::Delete => {
+                        app.remove_invalid_line();
+                        if app.diagnostics.items.is_empty() {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        app.diagnostics.next();
+                    }
+                    KeyCode::Up => {
+                        app.diagnostics.previous();
+                    }
+                    KeyCode::Char('g') => {
+                        app.diagnostics.first();
+                    }
+                    KeyCode::Char('G') => {
+                        app.diagnostics.last();
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
                     }
+                    _ => {}
+                },
+                CurrentScreen::Exiting => match key.code {
+                    KeyCode::Char('y') => match session.check_for_external_changes(true) {
+                        Ok(true) => {}
+                        Ok(false) => return Ok(true),
+                        Err(e) => return Err(io::Error::other(e.to_string())),
+                    },
                     KeyCode::Char('n') => {
                         return Ok(false);
                     }
                     KeyCode::Char('b') => {
                         app.current_screen = CurrentScreen::Main;
                     }
+                    KeyCode::Tab if sites > 1 => {
+                        session.next_site();
+                    }
+                    KeyCode::BackTab if sites > 1 => {
+                        session.previous_site();
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Archiving => match key.code {
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Char('y') => {
+                        app.archive_ended_lines();
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Backups => match key.code {
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        app.next_backup();
+                    }
+                    KeyCode::Up => {
+                        app.previous_backup();
+                    }
+                    KeyCode::PageDown => {
+                        app.scroll_backup_changes(10);
+                    }
+                    KeyCode::PageUp => {
+                        app.scroll_backup_changes(-10);
+                    }
+                    // If the backup can't be read, the error is shown in place of its changes
+                    KeyCode::Enter if app.restore_backup().is_ok() => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                // Once this site is dealt with, the next site changed by someone else is shown
+                CurrentScreen::Merging => match key.code {
+                    KeyCode::Char('m') | KeyCode::Char('o') => {
+                        if let KeyCode::Char('o') = key.code {
+                            app.merge = None;
+                        }
+                        match session.check_for_external_changes(false) {
+                            Ok(true) => {}
+                            Ok(false) => return Ok(true),
+                            Err(e) => return Err(io::Error::other(e.to_string())),
+                        }
+                    }
+                    KeyCode::Char('b') | KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                        session.cancel_merges();
+                    }
                     _ => {}
                 },
                 CurrentScreen::Removing => match key.code {
@@ -131,34 +454,83 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                         app.remove_schedule_line();
                     }
                     KeyCode::Down | KeyCode::Tab => {
-                        app.schedule_list.next();
+                        app.move_selection(1, true);
+                    }
+                    KeyCode::Up => {
+                        app.move_selection(-1, true);
+                    }
+                    KeyCode::Char('g') => {
+                        app.select_first_line();
+                    }
+                    KeyCode::Char('G') => {
+                        app.select_last_line();
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                        app.schedule_list.unselect();
+                    }
+                    KeyCode::PageDown => {
+                        app.move_selection(30, false);
+                    }
+                    KeyCode::PageUp => {
+                        app.move_selection(-30, false);
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Editing => match key.code {
+                    KeyCode::Char('q') => {
+                        app.current_screen = CurrentScreen::Exiting;
+                    }
+                    KeyCode::Enter if app.start_editing() => {
+                        app.current_screen = CurrentScreen::Adding;
+                        app.currently_editing = Some(CurrentlyEditing::Start);
+                    }
+                    KeyCode::Down | KeyCode::Tab => {
+                        app.move_selection(1, true);
                     }
                     KeyCode::Up => {
-                        app.schedule_list.previous();
+                        app.move_selection(-1, true);
                     }
                     KeyCode::Char('g') => {
-                        app.schedule_list.first();
+                        app.select_first_line();
                     }
                     KeyCode::Char('G') => {
-                        app.schedule_list.last();
+                        app.select_last_line();
                     }
                     KeyCode::Esc => {
                         app.current_screen = CurrentScreen::Main;
                         app.schedule_list.unselect();
                     }
                     KeyCode::PageDown => {
-                        app.schedule_list.state.scroll_down_by(30);
+                        app.move_selection(30, false);
                     }
                     KeyCode::PageUp => {
-                        app.schedule_list.state.scroll_up_by(30);
+                        app.move_selection(-30, false);
                     }
                     _ => {}
                 },
                 CurrentScreen::Selecting => match key.code {
+                    // Typing narrows down the experiment list. Every letter goes to the filter,
+                    // so g, G and q below only apply to the mode list, and the experiment list
+                    // is moved to its ends with Home and End
+                    KeyCode::Char(c)
+                        if matches!(app.currently_editing, Some(CurrentlyEditing::Experiment)) =>
+                    {
+                        app.push_experiment_filter(c);
+                    }
+                    KeyCode::Backspace => app.pop_experiment_filter(),
                     KeyCode::Char('q') => {
                         app.current_screen = CurrentScreen::Exiting;
                     }
-                    KeyCode::Enter | KeyCode::Left => {
+                    // An experiment must still be selected after filtering
+                    KeyCode::Enter | KeyCode::Left
+                        if app.experiment_list.state.selected().is_some()
+                            || !matches!(
+                                app.currently_editing,
+                                Some(CurrentlyEditing::Experiment)
+                            ) =>
+                    {
+                        app.clear_experiment_filter();
                         app.current_screen = CurrentScreen::Adding;
                     }
                     KeyCode::Down | KeyCode::Tab => match app.currently_editing {
@@ -171,19 +543,23 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                         Some(CurrentlyEditing::SchedulingMode) => app.mode_list.previous(),
                         _ => {}
                     },
-                    KeyCode::Char('g') => match app.currently_editing {
+                    KeyCode::Char('g') | KeyCode::Home => match app.currently_editing {
                         Some(CurrentlyEditing::Experiment) => app.experiment_list.first(),
                         Some(CurrentlyEditing::SchedulingMode) => app.mode_list.first(),
                         _ => {}
                     },
-                    KeyCode::Char('G') => match app.currently_editing {
+                    KeyCode::Char('G') | KeyCode::End => match app.currently_editing {
                         Some(CurrentlyEditing::Experiment) => app.experiment_list.last(),
                         Some(CurrentlyEditing::SchedulingMode) => app.mode_list.last(),
                         _ => {}
                     },
+                    KeyCode::Esc if !app.experiment_filter.is_empty() => {
+                        app.clear_experiment_filter();
+                    }
                     KeyCode::Esc => {
                         app.current_screen = CurrentScreen::Main;
                         app.currently_editing = None;
+                        app.cancel_entry();
                     }
                     _ => {}
                 },
@@ -192,38 +568,12 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                         if let Some(editing) = &app.currently_editing {
                             match editing {
                                 CurrentlyEditing::Done => match app.save_entry() {
-                                    Ok(_) => {
+                                    Ok(true) => {
                                         app.currently_editing = None;
                                         app.current_screen = CurrentScreen::Main;
                                     }
-                                    Err(e) => match e {
-                                        ScheduleError::InvalidDate(s) if s.contains("day") => {
-                                            app.currently_editing = Some(CurrentlyEditing::Day)
-                                        }
-                                        ScheduleError::InvalidDate(s) if s.contains("month") => {
-                                            app.currently_editing = Some(CurrentlyEditing::Month)
-                                        }
-                                        ScheduleError::InvalidDate(_) => {
-                                            app.currently_editing = Some(CurrentlyEditing::Year)
-                                        }
-                                        ScheduleError::InvalidTime(s) if s.contains("minute") => {
-                                            app.currently_editing = Some(CurrentlyEditing::Minute)
-                                        }
-                                        ScheduleError::InvalidTime(_) => {
-                                            app.currently_editing = Some(CurrentlyEditing::Hour)
-                                        }
-                                        ScheduleError::InvalidDuration(_) => {
-                                            app.currently_editing = Some(CurrentlyEditing::Duration)
-                                        }
-                                        ScheduleError::InvalidPriority(_) => {
-                                            app.currently_editing = Some(CurrentlyEditing::Priority)
-                                        }
-                                        ScheduleError::InvalidMode(_) => {
-                                            app.currently_editing =
-                                                Some(CurrentlyEditing::SchedulingMode)
-                                        }
-                                        _ => {}
-                                    },
+                                    Ok(false) => {}
+                                    Err(e) => app.currently_editing = Some(e.field().into()),
                                 },
                                 _ => {
                                     app.forward_toggle();
@@ -231,38 +581,33 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                             }
                         }
                     }
-                    KeyCode::End => {
-                        if let Some(_) = &app.currently_editing {
-                            app.currently_editing = Some(CurrentlyEditing::Done);
-                        }
+                    KeyCode::End if app.currently_editing.is_some() => {
+                        app.currently_editing = Some(CurrentlyEditing::Done);
                     }
                     KeyCode::Backspace => {
                         if let Some(editing) = &app.currently_editing {
                             match editing {
-                                CurrentlyEditing::Year => {
-                                    app.year_input.pop();
-                                }
-                                CurrentlyEditing::Month => {
-                                    app.month_input.pop();
+                                CurrentlyEditing::Start => {
+                                    app.start_input.pop();
                                 }
-                                CurrentlyEditing::Day => {
-                                    app.day_input.pop();
-                                }
-                                CurrentlyEditing::Hour => {
-                                    app.hour_input.pop();
-                                }
-                                CurrentlyEditing::Minute => {
-                                    app.minute_input.pop();
+                                CurrentlyEditing::Timezone => {
+                                    app.zone_input.pop();
                                 }
                                 CurrentlyEditing::Duration => {
                                     app.duration_input.pop();
                                 }
+                                CurrentlyEditing::End => {
+                                    app.end_input.pop();
+                                }
                                 CurrentlyEditing::Priority => {
                                     app.priority_input.pop();
                                 }
                                 CurrentlyEditing::Kwargs => {
                                     app.kwarg_input.pop();
                                 }
+                                CurrentlyEditing::Recurrence => {
+                                    app.recurrence_input.pop();
+                                }
                                 _ => {}
                             }
                         }
@@ -270,6 +615,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                     KeyCode::Esc => {
                         app.current_screen = CurrentScreen::Main;
                         app.currently_editing = None;
+                        app.cancel_entry();
                     }
                     KeyCode::Tab | KeyCode::Down => {
                         app.forward_toggle();
@@ -280,43 +626,38 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                     KeyCode::Char(value) => {
                         if let Some(editing) = &app.currently_editing {
                             match editing {
-                                CurrentlyEditing::Year => {
-                                    app.year_input.push(value);
-                                }
-                                CurrentlyEditing::Month => {
-                                    app.month_input.push(value);
-                                }
-                                CurrentlyEditing::Day => {
-                                    app.day_input.push(value);
+                                CurrentlyEditing::Start => {
+                                    app.start_input.push(value);
                                 }
-                                CurrentlyEditing::Hour => {
-                                    app.hour_input.push(value);
-                                }
-                                CurrentlyEditing::Minute => {
-                                    app.minute_input.push(value);
+                                CurrentlyEditing::Timezone => {
+                                    app.zone_input.push(value);
                                 }
                                 CurrentlyEditing::Duration => {
                                     app.duration_input.push(value);
                                 }
+                                CurrentlyEditing::End => {
+                                    app.end_input.push(value);
+                                }
                                 CurrentlyEditing::Priority => {
                                     app.priority_input.push(value);
                                 }
                                 CurrentlyEditing::Kwargs => {
                                     app.kwarg_input.push(value);
                                 }
+                                CurrentlyEditing::Recurrence => {
+                                    app.recurrence_input.push(value);
+                                }
                                 _ => {}
                             }
                         }
                     }
                     KeyCode::Right => {
-                        if let Some(editing) = &app.currently_editing {
-                            match editing {
-                                CurrentlyEditing::Experiment | CurrentlyEditing::SchedulingMode => {
-                                    app.current_screen = CurrentScreen::Selecting;
-                                    app.last_err = None;
-                                }
-                                _ => {}
-                            }
+                        if let Some(
+                            CurrentlyEditing::Experiment | CurrentlyEditing::SchedulingMode,
+                        ) = &app.currently_editing
+                        {
+                            app.current_screen = CurrentScreen::Selecting;
+                            app.last_err = None;
                         }
                     }
                     KeyCode::Left => {
@@ -1,31 +1,24 @@
+pub mod conflict;
+pub mod diff;
+pub mod document;
+mod error;
+mod kwargs;
+pub mod query;
+pub mod recurrence;
+pub mod resolve;
+pub mod timezone;
+pub mod when;
+
+pub use error::{Field, FieldError, ScheduleError};
+pub use kwargs::Kwargs;
+
 use crate::ui::{ALT_ROW_COLOR, BG_COLOR, NORMAL_ROW_COLOR, TEXT_COLOR};
 use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use ratatui::style::Stylize;
 use ratatui::text::Line;
 use ratatui::widgets::ListItem;
 use std::fmt::{Display, Formatter, Write};
-use thiserror::Error;
-
-#[derive(Error, Debug, Clone, PartialEq)]
-pub enum ScheduleError {
-    #[error("{0}")]
-    InvalidDate(String),
-
-    #[error("{0}")]
-    InvalidTime(String),
-
-    #[error("{0}")]
-    InvalidDuration(String),
-
-    #[error("{0}")]
-    InvalidPriority(String),
-
-    #[error("{0}")]
-    InvalidMode(String),
-
-    #[error("Missing fields")]
-    MissingFields,
-}
+use std::ops::Range;
 
 #[derive(Debug, Clone, Copy, Default, Ord, PartialOrd, Eq, PartialEq)]
 pub enum SchedulingMode {
@@ -44,7 +37,7 @@ impl Display for SchedulingMode {
     }
 }
 impl SchedulingMode {
-    pub fn to_list_item(&self) -> ListItem {
+    pub fn to_list_item(self) -> ListItem<'static> {
         ListItem::new(Line::styled(format!("{self}"), TEXT_COLOR)).bg(BG_COLOR)
     }
 }
@@ -73,9 +66,10 @@ impl TryFrom<&String> for ScdDuration {
         } else {
             let dur = parse_duration(value)?;
             if dur.num_minutes() < 1 {
-                return Err(ScheduleError::InvalidDuration(format!(
-                    "Expected minutes > 0, got {}",
-                    value.clone()
+                return Err(ScheduleError::InvalidDuration(FieldError::new(
+                    Field::Duration,
+                    value,
+                    "minutes > 0",
                 )));
             }
             Ok(ScdDuration::Finite(dur))
@@ -90,16 +84,16 @@ pub struct ScheduleLine {
     pub priority: u8,
     pub experiment: String,
     pub scheduling_mode: SchedulingMode,
-    pub kwargs: Vec<String>,
+    pub kwargs: Kwargs,
 }
 impl ScheduleLine {
     pub fn new(
         timestamp: DateTime<Utc>,
         duration: ScdDuration,
         priority: u8,
-        experiment: &String,
+        experiment: &str,
         scheduling_mode: &SchedulingMode,
-        kwargs: Vec<String>,
+        kwargs: Kwargs,
     ) -> Result<ScheduleLine, ScheduleError> {
         if (timestamp
             < NaiveDateTime::parse_from_str("20000101 00:00", "%Y%m%d %H:%M")
@@ -110,36 +104,44 @@ impl ScheduleLine {
                     .unwrap()
                     .and_utc())
         {
-            return Err(ScheduleError::InvalidDate(format!(
-                "Expected date between years 2000 and 2049, got {}",
-                timestamp
+            return Err(ScheduleError::InvalidDate(FieldError::new(
+                Field::Start,
+                &timestamp.format("%Y%m%d %H:%M").to_string(),
+                "date between years 2000 and 2050",
             )));
         }
         if let ScdDuration::Infinite = duration {
             if priority > 0 {
-                return Err(ScheduleError::InvalidPriority(
-                    "Cannot have priority > 0 for infinite schedule line".to_string(),
-                ));
+                return Err(ScheduleError::InvalidPriority(FieldError::new(
+                    Field::Priority,
+                    &priority.to_string(),
+                    "priority 0 for infinite schedule line",
+                )));
             }
         } else if let ScdDuration::Finite(dur) = duration {
             if dur.num_minutes() < 1 {
-                return Err(ScheduleError::InvalidDuration(format!(
-                    "Expected positive duration, got {}",
-                    dur
+                return Err(ScheduleError::InvalidDuration(FieldError::new(
+                    Field::Duration,
+                    &dur.num_minutes().to_string(),
+                    "positive duration",
                 )));
             }
         }
         if priority > 20 {
-            return Err(ScheduleError::InvalidPriority(format!("{priority} > 20")));
+            return Err(ScheduleError::InvalidPriority(FieldError::new(
+                Field::Priority,
+                &priority.to_string(),
+                "priority in range [0, 20]",
+            )));
         }
 
         Ok(ScheduleLine {
             timestamp,
             duration,
             priority,
-            experiment: experiment.clone(),
-            scheduling_mode: scheduling_mode.clone(),
-            kwargs: kwargs.clone(),
+            experiment: experiment.to_string(),
+            scheduling_mode: *scheduling_mode,
+            kwargs,
         })
     }
 }
@@ -147,47 +149,113 @@ impl TryFrom<&String> for ScheduleLine {
     type Error = ScheduleError;
 
     fn try_from(value: &String) -> Result<Self, Self::Error> {
-        let fields: Vec<String> = value.split(' ').map(|s| s.to_string()).collect();
-        if fields.len() < 6 {
-            Err(ScheduleError::MissingFields)?;
+        let tokens = tokenize(value);
+        if tokens.len() < 6 {
+            // The date and time tokens together make up the start
+            let missing = [
+                Field::Start,
+                Field::Start,
+                Field::Duration,
+                Field::Priority,
+                Field::Experiment,
+                Field::SchedulingMode,
+            ][tokens.len()];
+            return Err(ScheduleError::MissingFields(FieldError::new(
+                missing,
+                value,
+                "YYYYMMDD HH:MM duration priority experiment mode [kwargs]",
+            ))
+            .with_span(value.len()..value.len()));
         }
-        let timestamp = parse_date(&fields[0])?
-            .and_time(parse_time(&fields[1])?)
+        let span = |i: usize| tokens[i].0.clone();
+        let field = |i: usize| tokens[i].1;
+
+        let timestamp = parse_date(field(0))
+            .map_err(|e| e.with_span(span(0)))?
+            .and_time(parse_time(field(1)).map_err(|e| e.with_span(span(1)))?)
             .and_utc();
 
-        let priority: u8 = fields[3]
-            .parse()
-            .map_err(|_| ScheduleError::InvalidPriority(fields[3].clone()))?;
-        if priority > 20 {
-            return Err(ScheduleError::InvalidPriority(format!(
-                "{} > 20",
-                fields[3].clone()
-            )));
-        }
+        let duration =
+            ScdDuration::try_from(&field(2).to_string()).map_err(|e| e.with_span(span(2)))?;
 
-        let scheduling_mode = match fields[5].as_str() {
+        let priority: u8 = field(3).parse().map_err(|_| {
+            ScheduleError::InvalidPriority(FieldError::new(
+                Field::Priority,
+                field(3),
+                "priority in range [0, 20]",
+            ))
+            .with_span(span(3))
+        })?;
+
+        let scheduling_mode = match field(5) {
             "common" => SchedulingMode::Common,
             "discretionary" => SchedulingMode::Discretionary,
             "special" => SchedulingMode::Special,
-            _ => return Err(ScheduleError::InvalidMode(fields[5].clone())),
+            _ => {
+                return Err(ScheduleError::InvalidMode(FieldError::new(
+                    Field::SchedulingMode,
+                    field(5),
+                    "common, discretionary or special",
+                ))
+                .with_span(span(5)))
+            }
         };
 
+        let kwargs_start = span(5).end;
+        let kwargs =
+            Kwargs::parse(&value[kwargs_start..]).map_err(|e| e.offset_span(kwargs_start))?;
+
         ScheduleLine::new(
             timestamp,
-            ScdDuration::try_from(&fields[2])?,
+            duration,
             priority,
-            &fields[4].clone(),
+            field(4),
             &scheduling_mode,
-            fields[6..].into(),
+            kwargs,
         )
+        .map_err(|e| {
+            let span = match e.field() {
+                Field::Duration => span(2),
+                Field::Priority => span(3),
+                _ => span(0).start..span(1).end,
+            };
+            e.with_span(span)
+        })
     }
 }
+
+/// Splits `text` on whitespace, keeping the byte range of each token.
+fn tokenize(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push((s..i, &text[s..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s..text.len(), &text[s..]));
+    }
+    tokens
+}
 impl ScheduleLine {
+    /// Time at which the line stops being scheduled, or `None` if it runs indefinitely.
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        match self.duration {
+            ScdDuration::Infinite => None,
+            ScdDuration::Finite(dur) => Some(self.timestamp + dur),
+        }
+    }
+
     pub fn format(&self) -> String {
         let mut kwargs_string = String::new();
-        for kw in self.kwargs.iter() {
-            kwargs_string.push(' ');
-            kwargs_string.extend(kw.chars());
+        if !self.kwargs.is_empty() {
+            kwargs_string = format!(" {}", self.kwargs);
         }
 
         format!(
@@ -201,7 +269,7 @@ impl ScheduleLine {
         )
     }
 
-    pub fn to_list_item(&self, index: usize) -> ListItem {
+    pub fn to_list_item(&self, index: usize) -> ListItem<'_> {
         let bg_color = match index % 2 {
             0 => NORMAL_ROW_COLOR,
             _ => ALT_ROW_COLOR,
@@ -210,22 +278,40 @@ impl ScheduleLine {
     }
 }
 
-pub fn parse_date(date: &String) -> Result<NaiveDate, ScheduleError> {
-    NaiveDate::parse_from_str(date.as_str(), "%Y%m%d")
-        .map_err(|_| ScheduleError::InvalidDate(format!("Expected YYYYMMDD, got {}", date.clone())))
+pub fn parse_date(date: &str) -> Result<NaiveDate, ScheduleError> {
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|_| ScheduleError::InvalidDate(FieldError::new(Field::Start, date, "YYYYMMDD")))
 }
 
-pub fn parse_time(time: &String) -> Result<NaiveTime, ScheduleError> {
-    NaiveTime::parse_from_str(time.as_str(), "%H:%M")
-        .map_err(|_| ScheduleError::InvalidTime(format!("Expected HH:MM, got {}", time.clone())))
+pub fn parse_time(time: &str) -> Result<NaiveTime, ScheduleError> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| ScheduleError::InvalidTime(FieldError::new(Field::Start, time, "HH:MM")))
 }
 
-pub fn parse_duration(dur: &String) -> Result<Duration, ScheduleError> {
+/// Parses a point in time given as `now` or `YYYYMMDD HH:MM`.
+pub fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, ScheduleError> {
+    if timestamp.trim() == "now" {
+        return Ok(Utc::now());
+    }
+    let fields: Vec<&str> = timestamp.split_whitespace().collect();
+    if fields.len() != 2 {
+        return Err(ScheduleError::InvalidDate(FieldError::new(
+            Field::Start,
+            timestamp,
+            "YYYYMMDD HH:MM",
+        )));
+    }
+    Ok(parse_date(fields[0])?
+        .and_time(parse_time(fields[1])?)
+        .and_utc())
+}
+
+pub fn parse_duration(dur: &str) -> Result<Duration, ScheduleError> {
     Duration::try_minutes(dur.parse().map_err(|_| {
-        ScheduleError::InvalidDuration(format!("Expected minutes as i64, got {}", dur.clone()))
+        ScheduleError::InvalidDuration(FieldError::new(Field::Duration, dur, "minutes as i64"))
     })?)
     .ok_or_else(|| {
-        ScheduleError::InvalidDuration(format!("Expected minutes > 0, got {}", dur.clone()))
+        ScheduleError::InvalidDuration(FieldError::new(Field::Duration, dur, "minutes > 0"))
     })
 }
 
@@ -238,7 +324,7 @@ mod tests {
     #[test]
     fn test_parse_date() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            parse_date(&"20000101".to_string())?,
+            parse_date("20000101")?,
             NaiveDate::parse_from_str("20000101", "%Y%m%d")?
         );
         Ok(())
@@ -246,39 +332,60 @@ mod tests {
     #[test]
     fn test_parse_bad_date() {
         assert_eq!(
-            parse_date(&"20000000".to_string()),
-            Err(ScheduleError::InvalidDate(
-                "Expected YYYYMMDD, got 20000000".to_string()
-            ))
+            parse_date("20000000"),
+            Err(ScheduleError::InvalidDate(FieldError::new(
+                Field::Start,
+                "20000000",
+                "YYYYMMDD"
+            )))
         )
     }
 
     #[test]
     fn test_parse_time() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            parse_time(&"00:00".to_string())?,
+            parse_time("00:00")?,
             NaiveTime::parse_from_str("00:00", "%H:%M")?
         );
         assert_eq!(
-            parse_time(&"24:00".to_string()),
-            Err(ScheduleError::InvalidTime(
-                "Expected HH:MM, got 24:00".to_string()
-            ))
+            parse_time("24:00"),
+            Err(ScheduleError::InvalidTime(FieldError::new(
+                Field::Start,
+                "24:00",
+                "HH:MM"
+            )))
         );
         Ok(())
     }
 
     #[test]
-    fn test_parse_duration() -> Result<(), Box<dyn Error>> {
+    fn test_parse_timestamp() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            parse_duration(&"120".to_string())?,
-            Duration::new(7200, 0).unwrap()
+            parse_timestamp("20240101 12:30")?,
+            NaiveDateTime::parse_from_str("20240101 12:30", "%Y%m%d %H:%M")?.and_utc()
         );
         assert_eq!(
-            parse_duration(&"one hundred".to_string()),
-            Err(ScheduleError::InvalidDuration(
-                "Expected minutes as i64, got one hundred".to_string()
-            ))
+            parse_timestamp("20240101"),
+            Err(ScheduleError::InvalidDate(FieldError::new(
+                Field::Start,
+                "20240101",
+                "YYYYMMDD HH:MM"
+            )))
+        );
+        assert!(parse_timestamp("now").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_duration() -> Result<(), Box<dyn Error>> {
+        assert_eq!(parse_duration("120")?, Duration::new(7200, 0).unwrap());
+        assert_eq!(
+            parse_duration("one hundred"),
+            Err(ScheduleError::InvalidDuration(FieldError::new(
+                Field::Duration,
+                "one hundred",
+                "minutes as i64"
+            )))
         );
         Ok(())
     }
@@ -302,9 +409,9 @@ mod tests {
             NaiveDateTime::parse_from_str("20000101 00:00", "%Y%m%d %H:%M")?.and_utc(),
             ScdDuration::Finite(Duration::new(60, 0).unwrap()),
             0,
-            &"normalscan".to_string(),
+            "normalscan",
             &SchedulingMode::Common,
-            vec![],
+            Kwargs::default(),
         )?;
         assert_eq!(
             line,
@@ -331,7 +438,7 @@ mod tests {
             line,
             ScheduleLine::try_from(&"20000101 00:00 - 0 test discretionary".to_string())?
         );
-        line.kwargs.push("--embargo".to_string());
+        line.kwargs = Kwargs::parse("--embargo")?;
         assert_eq!(
             line,
             ScheduleLine::try_from(&"20000101 00:00 - 0 test discretionary --embargo".to_string())?
@@ -345,14 +452,14 @@ mod tests {
             NaiveDateTime::parse_from_str("20000101 00:00", "%Y%m%d %H:%M")?.and_utc(),
             ScdDuration::Infinite,
             0,
-            &"normalscan".to_string(),
+            "normalscan",
             &SchedulingMode::Common,
-            vec![],
+            Kwargs::default(),
         )?;
         assert_eq!(line.format(), "20000101 00:00 - 0 normalscan common");
         line.duration = ScdDuration::Finite(Duration::new(60, 0).unwrap());
         assert_eq!(line.format(), "20000101 00:00 1 0 normalscan common");
-        line.kwargs.push("--embargo".to_string());
+        line.kwargs = Kwargs::parse("--embargo")?;
         assert_eq!(
             line.format(),
             "20000101 00:00 1 0 normalscan common --embargo"
@@ -382,36 +489,74 @@ mod tests {
             line.format(),
             "20241231 23:59 1440 20 full_fov special --embargo"
         );
+
+        // Quoted kwargs come back exactly as they were written
+        let text = "20241231 23:59 60 5 full_fov special comment=\"two  words\" --embargo";
+        assert_eq!(ScheduleLine::try_from(&text.to_string())?.format(), text);
         Ok(())
     }
 
     #[test]
     fn make_schedule_line() {
-        assert_eq!(
-            ScheduleLine::try_from(&"20000101 00:00 120 25 normalscan common".to_string()),
-            Err(ScheduleError::InvalidPriority("25 > 20".to_string()))
+        let check = |line: &str, field: Field, span: Range<usize>, message: &str| {
+            let err = ScheduleLine::try_from(&line.to_string()).unwrap_err();
+            assert_eq!(err.field(), field);
+            assert_eq!(err.detail().span, Some(span));
+            assert_eq!(err.to_string(), message);
+        };
+        check(
+            "20000101 00:00 120 5 normalscan common a=1 b='oops",
+            Field::Kwargs,
+            45..50,
+            "Expected closing ', got 'oops",
         );
-        assert_eq!(
-            ScheduleLine::try_from(&"20000101 00:00 120 -1 normalscan common".to_string()),
-            Err(ScheduleError::InvalidPriority("-1".to_string()))
+        check(
+            "20000101 00:00 120 25 normalscan common",
+            Field::Priority,
+            19..21,
+            "Expected priority in range [0, 20], got 25",
         );
-        assert_eq!(
-            ScheduleLine::try_from(&"20000101 00:00 -10 20 normalscan common".to_string()),
-            Err(ScheduleError::InvalidDuration(
-                "Expected minutes > 0, got -10".to_string()
-            ))
+        check(
+            "20000101 00:00 120 -1 normalscan common",
+            Field::Priority,
+            19..21,
+            "Expected priority in range [0, 20], got -1",
         );
-        assert_eq!(
-            ScheduleLine::try_from(&"20000101 24:00 120 20 normalscan common".to_string()),
-            Err(ScheduleError::InvalidTime(
-                "Expected HH:MM, got 24:00".to_string()
-            ))
+        check(
+            "20000101 00:00 -10 20 normalscan common",
+            Field::Duration,
+            15..18,
+            "Expected minutes > 0, got -10",
         );
-        assert_eq!(
-            ScheduleLine::try_from(&"20000101 0000 120 20 normalscan common".to_string()),
-            Err(ScheduleError::InvalidTime(
-                "Expected HH:MM, got 0000".to_string()
-            ))
+        check(
+            "20000101 24:00 120 20 normalscan common",
+            Field::Start,
+            9..14,
+            "Expected HH:MM, got 24:00",
+        );
+        check(
+            "20000101 0000 120 20 normalscan common",
+            Field::Start,
+            9..13,
+            "Expected HH:MM, got 0000",
+        );
+        check(
+            "20000101 00:00 - 5 normalscan common",
+            Field::Priority,
+            17..18,
+            "Expected priority 0 for infinite schedule line, got 5",
+        );
+        check(
+            "20000101  00:00 120 0 normalscan sometimes",
+            Field::SchedulingMode,
+            33..42,
+            "Expected common, discretionary or special, got sometimes",
+        );
+        check(
+            "20000101 00:00 120",
+            Field::Priority,
+            18..18,
+            "Missing fields, expected YYYYMMDD HH:MM duration priority experiment mode [kwargs]",
         );
     }
 }
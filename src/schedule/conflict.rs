@@ -0,0 +1,147 @@
+//! Detection of schedule lines that are valid, but probably not what the operator intended.
+
+use crate::schedule::resolve::resolve;
+use crate::schedule::ScheduleLine;
+use chrono::{DateTime, Utc};
+use std::fmt::{Display, Formatter};
+
+/// A potential problem with a schedule line that does not prevent it from being saved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduleWarning {
+    /// The line overlaps another finite line with the same priority.
+    Overlap(ScheduleLine),
+    /// The line never runs, as the listed lines take precedence over its whole window.
+    Shadowed(Vec<ScheduleLine>),
+    /// The line starts before the current time.
+    InPast,
+    /// The kwarg, as written, was not found in the experiment, which may still accept it.
+    UnknownKwarg(String),
+}
+impl Display for ScheduleWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Overlap(other) => {
+                write!(f, "Overlaps same priority line: {}", other.format())
+            }
+            Self::Shadowed(others) => {
+                write!(f, "Never runs, preempted by:")?;
+                for other in others.iter() {
+                    write!(f, "\n  {}", other.format())?;
+                }
+                Ok(())
+            }
+            Self::InPast => write!(f, "Starts in the past"),
+            Self::UnknownKwarg(kwarg) => write!(f, "Kwarg not known to the experiment: {kwarg}"),
+        }
+    }
+}
+
+/// Checks `line` against the `existing` lines of a schedule, returning any warnings.
+pub fn check_line(
+    line: &ScheduleLine,
+    existing: &[ScheduleLine],
+    now: DateTime<Utc>,
+) -> Vec<ScheduleWarning> {
+    let mut warnings = vec![];
+
+    if line.timestamp < now {
+        warnings.push(ScheduleWarning::InPast);
+    }
+
+    // Infinite lines are expected to overlap, as each one replaces the last as the baseline
+    let Some(end) = line.end_time() else {
+        return warnings;
+    };
+
+    for other in existing.iter() {
+        if other.priority != line.priority {
+            continue;
+        }
+        if let Some(other_end) = other.end_time() {
+            if other.timestamp < end && line.timestamp < other_end {
+                warnings.push(ScheduleWarning::Overlap(other.clone()));
+            }
+        }
+    }
+
+    let mut combined = existing.to_vec();
+    combined.push(line.clone());
+    let candidate = &combined[combined.len() - 1];
+    let segments = resolve(&combined, line.timestamp, end);
+    if !segments.iter().any(|s| std::ptr::eq(s.line, candidate)) {
+        let mut winners: Vec<ScheduleLine> = vec![];
+        for segment in segments.iter() {
+            if !winners.contains(segment.line) {
+                winners.push(segment.line.clone());
+            }
+        }
+        warnings.push(ScheduleWarning::Shadowed(winners));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn line(s: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&s.to_string()).unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str("20240101 00:00", "%Y%m%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn no_warnings_over_baseline() {
+        let existing = vec![line("20240101 00:00 - 0 normalscan common")];
+        let new = line("20240102 00:00 60 0 twofsound common");
+        assert!(check_line(&new, &existing, now()).is_empty());
+        let new = line("20240103 00:00 - 0 twofsound common");
+        assert!(check_line(&new, &existing, now()).is_empty());
+    }
+
+    #[test]
+    fn same_priority_overlap() {
+        let existing = vec![
+            line("20240101 00:00 - 0 normalscan common"),
+            line("20240102 00:00 120 5 full_fov special"),
+        ];
+        let new = line("20240102 01:00 120 5 twofsound special");
+        assert_eq!(
+            check_line(&new, &existing, now()),
+            vec![ScheduleWarning::Overlap(existing[1].clone())]
+        );
+        let new = line("20240102 02:00 120 5 twofsound special");
+        assert!(check_line(&new, &existing, now()).is_empty());
+    }
+
+    #[test]
+    fn shadowed_by_higher_priority() {
+        let existing = vec![
+            line("20240101 00:00 - 0 normalscan common"),
+            line("20240102 00:00 120 10 full_fov special"),
+            line("20240102 02:00 120 15 twofsound special"),
+        ];
+        let new = line("20240102 01:00 120 5 normalscan special");
+        assert_eq!(
+            check_line(&new, &existing, now()),
+            vec![ScheduleWarning::Shadowed(vec![
+                existing[1].clone(),
+                existing[2].clone()
+            ])]
+        );
+        let new = line("20240102 03:00 120 5 normalscan special");
+        assert!(check_line(&new, &existing, now()).is_empty());
+    }
+
+    #[test]
+    fn starts_in_past() {
+        let new = line("20231231 23:59 - 0 normalscan common");
+        assert_eq!(check_line(&new, &[], now()), vec![ScheduleWarning::InPast]);
+    }
+}
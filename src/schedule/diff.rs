@@ -0,0 +1,183 @@
+//! Line-by-line differences between two versions of a schedule file.
+
+use std::fmt::{Display, Formatter};
+
+/// A line of the difference between two texts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// A line found in both texts.
+    Same(String),
+    /// A line found only in the old text.
+    Removed(String),
+    /// A line found only in the new text.
+    Added(String),
+}
+impl Display for DiffLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Same(x) => write!(f, "  {x}"),
+            Self::Removed(x) => write!(f, "- {x}"),
+            Self::Added(x) => write!(f, "+ {x}"),
+        }
+    }
+}
+
+/// Finds the lines that were removed from `old` and added to make `new`, keeping as many lines
+/// unchanged as possible.
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Versions of a schedule mostly differ in a few lines, so only the part between the lines
+    // they start and end with in common needs comparing line by line
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut lines: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|x| DiffLine::Same(x.to_string()))
+        .collect();
+    diff_changed(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+        &mut lines,
+    );
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|x| DiffLine::Same(x.to_string())),
+    );
+    lines
+}
+
+/// Adds the difference between `old` and `new` to `lines`, using Myers' algorithm to find the
+/// fewest lines to remove and add. This takes time proportional to the length of the texts times
+/// the number of lines that differ, rather than the product of their lengths.
+fn diff_changed(old: &[&str], new: &[&str], lines: &mut Vec<DiffLine>) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    // Furthest line of `old` reached on each diagonal k = x - y, stored at index k + offset
+    let offset = max + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    // The furthest lines reached on diagonals -d to d before each step d
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max {
+        trace.push(furthest[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && furthest[i - 1] < furthest[i + 1]) {
+                furthest[i + 1]
+            } else {
+                furthest[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Follow the steps back from the end of both texts
+    let mut changes = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, reached) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| reached[(k + d) as usize];
+        let (previous_x, previous_y) = if d == 0 {
+            (0, 0)
+        } else if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            (at(k + 1), at(k + 1) - (k + 1))
+        } else {
+            (at(k - 1), at(k - 1) - (k - 1))
+        };
+        while x > previous_x && y > previous_y {
+            changes.push(DiffLine::Same(old[x as usize - 1].to_string()));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == previous_x {
+                changes.push(DiffLine::Added(new[y as usize - 1].to_string()));
+            } else {
+                changes.push(DiffLine::Removed(old[x as usize - 1].to_string()));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    lines.extend(changes.into_iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines() {
+        let old = "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240201 00:00 60 10 twofsound common\n";
+        let new = "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240201 00:00 60 15 twofsound common\n\
+            20240301 00:00 60 10 full_fov common\n";
+        assert_eq!(
+            diff(old, new),
+            vec![
+                DiffLine::Same("# Comment".to_string()),
+                DiffLine::Same("20240101 00:00 - 0 normalscan common".to_string()),
+                DiffLine::Removed("20240201 00:00 60 10 twofsound common".to_string()),
+                DiffLine::Added("20240201 00:00 60 15 twofsound common".to_string()),
+                DiffLine::Added("20240301 00:00 60 10 full_fov common".to_string()),
+            ]
+        );
+        assert!(diff(old, old)
+            .iter()
+            .all(|x| matches!(x, DiffLine::Same(_))));
+        assert_eq!(diff("", "a"), vec![DiffLine::Added("a".to_string())]);
+    }
+
+    #[test]
+    fn diff_long_files() {
+        // A few changes far apart in a long file are found without comparing every pair of lines
+        let old: Vec<String> = (0..100_000).map(|i| format!("line {i}")).collect();
+        let mut new = old.clone();
+        new[50_000] = "changed".to_string();
+        new.insert(99_999, "added".to_string());
+        let lines = diff(&old.join("\n"), &new.join("\n"));
+        assert_eq!(lines.len(), 100_002);
+        assert_eq!(lines[50_000], DiffLine::Removed("line 50000".to_string()));
+        assert_eq!(lines[50_001], DiffLine::Added("changed".to_string()));
+        assert_eq!(lines[100_000], DiffLine::Added("added".to_string()));
+        assert_eq!(lines[100_001], DiffLine::Same("line 99999".to_string()));
+
+        // Repeated lines where the common start and end meet
+        assert_eq!(
+            diff("a\na\n", "a\n"),
+            vec![
+                DiffLine::Same("a".to_string()),
+                DiffLine::Removed("a".to_string())
+            ]
+        );
+        assert_eq!(
+            diff("a\nb\na\n", "a\na\n"),
+            vec![
+                DiffLine::Same("a".to_string()),
+                DiffLine::Removed("b".to_string()),
+                DiffLine::Same("a".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,516 @@
+//! Lossless representation of a schedule file.
+//!
+//! Comments, blank lines and the exact text of every schedule line are kept, so that saving a
+//! schedule only rewrites the lines that were actually changed. Comments and blank lines stay
+//! where they are relative to the schedule lines around them, and new lines are inserted directly
+//! after the line that precedes them chronologically.
+
+use crate::schedule::{ScheduleError, ScheduleLine};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// A single line of a schedule file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    /// A schedule line, along with the text it was read from. The text is `None` for lines that
+    /// have been added or changed since the file was read.
+    Line {
+        line: ScheduleLine,
+        raw: Option<String>,
+    },
+    /// A comment, starting with `#`.
+    Comment(String),
+    /// A line containing only whitespace.
+    Blank(String),
+    /// A line that could not be parsed, which is kept as-is. `line_number` is the 1-based
+    /// number of the line in the file it was read from.
+    Invalid {
+        line_number: usize,
+        raw: String,
+        error: ScheduleError,
+    },
+}
+impl Entry {
+    /// Text of the entry as it should be written to file.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Line { raw: Some(raw), .. } => raw.clone(),
+            Self::Line { line, raw: None } => line.format(),
+            Self::Comment(raw) | Self::Blank(raw) | Self::Invalid { raw, .. } => raw.clone(),
+        }
+    }
+}
+
+/// A line of a schedule file that could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineDiagnostic {
+    pub line_number: usize,
+    pub raw: String,
+    pub error: ScheduleError,
+}
+impl Display for LineDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}: {}", self.line_number, self.error, self.raw)
+    }
+}
+impl std::error::Error for LineDiagnostic {}
+impl LineDiagnostic {
+    /// Renders the diagnostic in the style of a compiler error, pointing at the offending field.
+    pub fn report(&self) -> String {
+        self.error.report(&self.raw)
+    }
+}
+
+/// An edit of a line that someone else changed or removed before it could be merged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub original: ScheduleLine,
+    pub modified: ScheduleLine,
+}
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was changed or removed by someone else, so {} is added as a new line",
+            self.original.format(),
+            self.modified.format()
+        )
+    }
+}
+
+/// The contents of a schedule file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScdDocument {
+    pub entries: Vec<Entry>,
+    trailing_newline: bool,
+}
+impl ScdDocument {
+    /// Parses the text of a schedule file. Lines that cannot be parsed are kept as
+    /// `Entry::Invalid` rather than causing an error.
+    pub fn parse(text: &str) -> ScdDocument {
+        let trailing_newline = text.is_empty() || text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+        let entries = if text.is_empty() {
+            vec![]
+        } else {
+            body.split('\n')
+                .enumerate()
+                .map(|(i, raw)| Self::parse_entry(i + 1, raw))
+                .collect()
+        };
+        ScdDocument {
+            entries,
+            trailing_newline,
+        }
+    }
+
+    fn parse_entry(line_number: usize, raw: &str) -> Entry {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            return Entry::Blank(raw.to_string());
+        }
+        if trimmed.starts_with('#') {
+            return Entry::Comment(raw.to_string());
+        }
+        match ScheduleLine::try_from(&raw.to_string()) {
+            Ok(line) => Entry::Line {
+                line,
+                raw: Some(raw.to_string()),
+            },
+            Err(error) => Entry::Invalid {
+                line_number,
+                raw: raw.to_string(),
+                error: error.with_line(line_number),
+            },
+        }
+    }
+
+    /// Records `path` as the file that the document was read from, so that errors in unparseable
+    /// lines can point at it.
+    pub fn set_path(&mut self, path: &Path) {
+        for entry in self.entries.iter_mut() {
+            if let Entry::Invalid { error, .. } = entry {
+                *error = error.clone().with_path(path);
+            }
+        }
+    }
+
+    /// All valid schedule lines, in file order.
+    pub fn lines(&self) -> Vec<ScheduleLine> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Line { line, .. } => Some(line.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every line that could not be parsed, in file order.
+    pub fn diagnostics(&self) -> Vec<LineDiagnostic> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Invalid {
+                    line_number,
+                    raw,
+                    error,
+                } => Some(LineDiagnostic {
+                    line_number: *line_number,
+                    raw: raw.clone(),
+                    error: error.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Index of the invalid entry read from `line_number`.
+    fn invalid_position(&self, line_number: usize) -> Option<usize> {
+        self.entries.iter().position(
+            |entry| matches!(entry, Entry::Invalid { line_number: x, .. } if *x == line_number),
+        )
+    }
+
+    /// Replaces the invalid entry read from `line_number` with `line`, returning whether it
+    /// was found.
+    pub fn fix_invalid(&mut self, line_number: usize, line: ScheduleLine) -> bool {
+        match self.invalid_position(line_number) {
+            Some(i) => {
+                self.entries[i] = Entry::Line { line, raw: None };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the invalid entry read from `line_number`, returning its text if it was found.
+    pub fn remove_invalid(&mut self, line_number: usize) -> Option<String> {
+        let i = self.invalid_position(line_number)?;
+        Some(self.entries.remove(i).text())
+    }
+
+    /// Index of the first entry holding `line`.
+    fn position(&self, line: &ScheduleLine) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::Line { line: x, .. } if x == line))
+    }
+
+    /// Inserts `line` directly after the latest line that sorts before it, or before the first
+    /// schedule line if there is none.
+    pub fn insert(&mut self, line: ScheduleLine) {
+        let mut first_line = None;
+        let mut predecessor = None;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if let Entry::Line { line: x, .. } = entry {
+                first_line.get_or_insert(i);
+                if *x <= line {
+                    predecessor = Some(i);
+                }
+            }
+        }
+        let index = match (predecessor, first_line) {
+            (Some(i), _) => i + 1,
+            (None, Some(i)) => i,
+            (None, None) => self.entries.len(),
+        };
+        self.entries.insert(index, Entry::Line { line, raw: None });
+    }
+
+    /// Removes the first entry holding `line`, returning whether it was found. Comments and
+    /// blank lines around it are kept.
+    pub fn remove(&mut self, line: &ScheduleLine) -> bool {
+        match self.position(line) {
+            Some(i) => {
+                self.entries.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the first entry holding `old` with `new` in place, returning whether it was found.
+    pub fn replace(&mut self, old: &ScheduleLine, new: ScheduleLine) -> bool {
+        match self.position(old) {
+            Some(i) => {
+                self.entries[i] = Entry::Line {
+                    line: new,
+                    raw: None,
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Brings the document in line with `lines`, touching as few entries as possible.
+    ///
+    /// Each `(old, new)` pair in `modifications` is applied in place so the new line keeps the
+    /// position of the old one. Entries for lines that are not in `lines` are removed, and lines
+    /// that are not yet in the document are inserted.
+    pub fn update(
+        &mut self,
+        lines: &[ScheduleLine],
+        modifications: &[(ScheduleLine, ScheduleLine)],
+    ) {
+        for (old, new) in modifications.iter() {
+            if lines.contains(new) {
+                self.replace(old, new.clone());
+            }
+        }
+
+        let mut remaining = lines.to_vec();
+        self.entries.retain(|entry| match entry {
+            Entry::Line { line, .. } => match remaining.iter().position(|x| x == line) {
+                Some(i) => {
+                    remaining.remove(i);
+                    true
+                }
+                None => false,
+            },
+            _ => true,
+        });
+
+        remaining.sort();
+        for line in remaining {
+            self.insert(line);
+        }
+    }
+
+    /// Applies changes that were made to an earlier version of the document, for when someone
+    /// else has changed the schedule file in the meantime.
+    ///
+    /// `resolved_invalid` holds the text of unparseable lines that were removed or fixed. Lines
+    /// that were already removed, or already added, by someone else are left as they are. An
+    /// edit of a line that no longer exists can't be applied in place, so the edited line is
+    /// inserted as a new line and reported as a conflict.
+    pub fn merge(
+        &mut self,
+        additions: &[ScheduleLine],
+        deletions: &[ScheduleLine],
+        modifications: &[(ScheduleLine, ScheduleLine)],
+        resolved_invalid: &[String],
+    ) -> Vec<MergeConflict> {
+        for text in resolved_invalid.iter() {
+            if let Some(i) = self
+                .entries
+                .iter()
+                .position(|entry| matches!(entry, Entry::Invalid { raw, .. } if raw == text))
+            {
+                self.entries.remove(i);
+            }
+        }
+        for line in deletions.iter() {
+            self.remove(line);
+        }
+
+        let mut conflicts = vec![];
+        for (old, new) in modifications.iter() {
+            if self.position(new).is_some() || self.replace(old, new.clone()) {
+                continue;
+            }
+            self.insert(new.clone());
+            conflicts.push(MergeConflict {
+                original: old.clone(),
+                modified: new.clone(),
+            });
+        }
+        for line in additions.iter() {
+            if self.position(line).is_none() {
+                self.insert(line.clone());
+            }
+        }
+        conflicts
+    }
+}
+impl Display for ScdDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(&entry.text())?;
+        }
+        if self.trailing_newline && !self.entries.is_empty() {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "# Baseline\n\
+        20240101 00:00 - 0 normalscan common\n\
+        \n\
+        # Special time for the campaign\n\
+        20240301 00:00  120 10 full_fov special\n\
+        20240401 00:00 60 5 twofsound discretionary\n";
+
+    fn line(s: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let doc = ScdDocument::parse(TEXT);
+        assert_eq!(doc.to_string(), TEXT);
+        assert_eq!(doc.lines().len(), 3);
+        assert_eq!(
+            doc.lines()[1],
+            line("20240301 00:00 120 10 full_fov special")
+        );
+
+        let no_newline = "20240101 00:00 - 0 normalscan common";
+        assert_eq!(ScdDocument::parse(no_newline).to_string(), no_newline);
+        assert_eq!(ScdDocument::parse("").to_string(), "");
+    }
+
+    #[test]
+    fn keeps_invalid_lines() {
+        let text = "20240101 00:00 - 0 normalscan common\nnot a schedule line\n";
+        let doc = ScdDocument::parse(text);
+        assert!(matches!(
+            doc.entries[1],
+            Entry::Invalid {
+                line_number: 2,
+                error: ScheduleError::MissingFields(_),
+                ..
+            }
+        ));
+        assert_eq!(doc.to_string(), text);
+    }
+
+    #[test]
+    fn merge_onto_changed_file() {
+        // The file as someone else saved it after we loaded it
+        let mut theirs = ScdDocument::parse(
+            "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240201 00:00 60 10 twofsound common\n\
+            20240301 00:00 60 10 full_fov common\n\
+            garbage\n\
+            20240501 00:00 60 10 normalscan special\n",
+        );
+        let conflicts = theirs.merge(
+            &[
+                line("20240401 00:00 60 10 themisscan common"),
+                line("20240501 00:00 60 10 normalscan special"),
+            ],
+            &[line("20240201 00:00 60 10 twofsound common")],
+            &[
+                (
+                    line("20240301 00:00 60 10 full_fov common"),
+                    line("20240301 00:00 60 15 full_fov common"),
+                ),
+                (
+                    line("20240115 00:00 60 10 normalscan common"),
+                    line("20240115 00:00 60 15 normalscan common"),
+                ),
+            ],
+            &["garbage".to_string()],
+        );
+        assert_eq!(
+            conflicts,
+            vec![MergeConflict {
+                original: line("20240115 00:00 60 10 normalscan common"),
+                modified: line("20240115 00:00 60 15 normalscan common"),
+            }]
+        );
+        assert_eq!(
+            theirs.to_string(),
+            "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240115 00:00 60 15 normalscan common\n\
+            20240301 00:00 60 15 full_fov common\n\
+            20240401 00:00 60 10 themisscan common\n\
+            20240501 00:00 60 10 normalscan special\n"
+        );
+    }
+
+    #[test]
+    fn diagnostics_and_fixes() {
+        let text = "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240101 00:00 - 25 normalscan common\n\
+            20240101 00:00 60\n";
+        let mut doc = ScdDocument::parse(text);
+        let diagnostics = doc.diagnostics();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|x| (x.line_number, x.raw.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, "20240101 00:00 - 25 normalscan common"),
+                (4, "20240101 00:00 60"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].report(),
+            "error: Expected priority 0 for infinite schedule line, got 25\n\
+            \x20--> <schedule>:3:18\n\
+            \x20 |\n\
+            3 | 20240101 00:00 - 25 normalscan common\n\
+            \x20 |                  ^^ expected priority 0 for infinite schedule line\n"
+        );
+        assert!(matches!(
+            diagnostics[1].error,
+            ScheduleError::MissingFields(_)
+        ));
+
+        assert_eq!(doc.remove_invalid(4), Some("20240101 00:00 60".to_string()));
+        assert_eq!(doc.remove_invalid(4), None);
+        assert!(doc.fix_invalid(3, line("20240102 00:00 - 0 normalscan common")));
+        assert!(doc.diagnostics().is_empty());
+        assert_eq!(
+            doc.to_string(),
+            "# Comment\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240102 00:00 - 0 normalscan common\n"
+        );
+    }
+
+    #[test]
+    fn insert_after_predecessor() {
+        let mut doc = ScdDocument::parse(TEXT);
+        doc.insert(line("20240201 00:00 60 5 normalscan special"));
+        doc.insert(line("20231201 00:00 - 0 normalscan common"));
+        assert_eq!(
+            doc.to_string(),
+            "# Baseline\n\
+            20231201 00:00 - 0 normalscan common\n\
+            20240101 00:00 - 0 normalscan common\n\
+            20240201 00:00 60 5 normalscan special\n\
+            \n\
+            # Special time for the campaign\n\
+            20240301 00:00  120 10 full_fov special\n\
+            20240401 00:00 60 5 twofsound discretionary\n"
+        );
+    }
+
+    #[test]
+    fn update_only_touches_changed_lines() {
+        let mut doc = ScdDocument::parse(TEXT);
+        let old = line("20240301 00:00 120 10 full_fov special");
+        let new = line("20240301 00:00 240 10 full_fov special");
+        let lines = vec![
+            line("20240101 00:00 - 0 normalscan common"),
+            new.clone(),
+            line("20240501 00:00 60 5 normalscan special"),
+        ];
+        doc.update(&lines, &[(old, new)]);
+        assert_eq!(
+            doc.to_string(),
+            "# Baseline\n\
+            20240101 00:00 - 0 normalscan common\n\
+            \n\
+            # Special time for the campaign\n\
+            20240301 00:00 240 10 full_fov special\n\
+            20240501 00:00 60 5 normalscan special\n"
+        );
+    }
+}
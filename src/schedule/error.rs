@@ -0,0 +1,269 @@
+//! Errors raised while reading or creating schedule lines.
+
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Field of a schedule line, matching the fields of the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The start of a line, as `YYYYMMDD HH:MM` in schedule files or in any of the forms of
+    /// [`super::when`] in the editor.
+    Start,
+    Timezone,
+    Duration,
+    /// The end of a line, given instead of its duration.
+    End,
+    Priority,
+    Experiment,
+    SchedulingMode,
+    Kwargs,
+    Recurrence,
+}
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Start => "start",
+            Self::Timezone => "time zone",
+            Self::Duration => "duration",
+            Self::End => "end",
+            Self::Priority => "priority",
+            Self::Experiment => "experiment",
+            Self::SchedulingMode => "scheduling mode",
+            Self::Kwargs => "kwargs",
+            Self::Recurrence => "recurrence",
+        })
+    }
+}
+
+/// Where in a schedule file a line was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: Option<PathBuf>,
+    /// 1-based line number.
+    pub line_number: usize,
+}
+
+/// Details of a field that could not be turned into part of a schedule line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The offending field.
+    pub field: Field,
+    /// The text that was given for the field.
+    pub token: String,
+    /// Description of what the field should have been.
+    pub expected: String,
+    /// Byte range of `token` within the schedule line, when parsed from text.
+    pub span: Option<Range<usize>>,
+    /// The file and line the schedule line was read from, when known.
+    pub location: Option<Location>,
+}
+impl FieldError {
+    pub fn new(field: Field, token: &str, expected: &str) -> FieldError {
+        FieldError {
+            field,
+            token: token.to_string(),
+            expected: expected.to_string(),
+            span: None,
+            location: None,
+        }
+    }
+}
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Expected {}, got {}", self.expected, self.token)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ScheduleError {
+    #[error("{0}")]
+    InvalidDate(FieldError),
+
+    #[error("{0}")]
+    InvalidTime(FieldError),
+
+    #[error("{0}")]
+    InvalidTimezone(FieldError),
+
+    #[error("{0}")]
+    InvalidDuration(FieldError),
+
+    #[error("{0}")]
+    InvalidPriority(FieldError),
+
+    #[error("{0}")]
+    InvalidMode(FieldError),
+
+    #[error("{0}")]
+    InvalidKwargs(FieldError),
+
+    #[error("{0}")]
+    InvalidRecurrence(FieldError),
+
+    /// A line that is valid on its own, but cannot be added to the schedule as it is, e.g.
+    /// because the schedule already has it.
+    #[error("{0}")]
+    InvalidChange(FieldError),
+
+    #[error("Missing fields, expected {}", .0.expected)]
+    MissingFields(FieldError),
+}
+impl ScheduleError {
+    pub fn detail(&self) -> &FieldError {
+        match self {
+            Self::InvalidDate(x)
+            | Self::InvalidTime(x)
+            | Self::InvalidTimezone(x)
+            | Self::InvalidDuration(x)
+            | Self::InvalidPriority(x)
+            | Self::InvalidMode(x)
+            | Self::InvalidKwargs(x)
+            | Self::InvalidRecurrence(x)
+            | Self::InvalidChange(x)
+            | Self::MissingFields(x) => x,
+        }
+    }
+
+    fn detail_mut(&mut self) -> &mut FieldError {
+        match self {
+            Self::InvalidDate(x)
+            | Self::InvalidTime(x)
+            | Self::InvalidTimezone(x)
+            | Self::InvalidDuration(x)
+            | Self::InvalidPriority(x)
+            | Self::InvalidMode(x)
+            | Self::InvalidKwargs(x)
+            | Self::InvalidRecurrence(x)
+            | Self::InvalidChange(x)
+            | Self::MissingFields(x) => x,
+        }
+    }
+
+    /// The field that the error refers to.
+    pub fn field(&self) -> Field {
+        self.detail().field
+    }
+
+    /// Sets the span of the offending text within its line, unless one is already set.
+    pub fn with_span(mut self, span: Range<usize>) -> ScheduleError {
+        self.detail_mut().span.get_or_insert(span);
+        self
+    }
+
+    /// Moves the span of the offending text `offset` bytes later, for text that was parsed on its
+    /// own but is part of a longer line.
+    pub fn offset_span(mut self, offset: usize) -> ScheduleError {
+        if let Some(span) = self.detail_mut().span.as_mut() {
+            *span = span.start + offset..span.end + offset;
+        }
+        self
+    }
+
+    /// Sets the line number that the offending line was read from.
+    pub fn with_line(mut self, line_number: usize) -> ScheduleError {
+        let detail = self.detail_mut();
+        match detail.location.as_mut() {
+            Some(location) => location.line_number = line_number,
+            None => {
+                detail.location = Some(Location {
+                    path: None,
+                    line_number,
+                })
+            }
+        }
+        self
+    }
+
+    /// Sets the file that the offending line was read from. Has no effect unless the line number
+    /// is already known.
+    pub fn with_path(mut self, path: &Path) -> ScheduleError {
+        if let Some(location) = self.detail_mut().location.as_mut() {
+            location.path = Some(path.to_path_buf());
+        }
+        self
+    }
+
+    /// Renders the error in the style of a compiler diagnostic, pointing at the offending part of
+    /// `source`, the text of the line it came from.
+    pub fn report(&self, source: &str) -> String {
+        let detail = self.detail();
+        let mut report = format!("error: {self}\n");
+        let line_number = detail.location.as_ref().map(|x| x.line_number);
+        let gutter = " ".repeat(line_number.map_or(0, |x| x.to_string().len()) + 1);
+        let column = detail.span.as_ref().map_or(0, |span| {
+            source[..span.start.min(source.len())].chars().count()
+        });
+
+        if let Some(location) = &detail.location {
+            let path = location
+                .path
+                .as_ref()
+                .map_or("<schedule>".to_string(), |x| x.display().to_string());
+            report.push_str(&format!(
+                "{}--> {path}:{}:{}\n",
+                &gutter[1..],
+                location.line_number,
+                column + 1
+            ));
+        }
+        report.push_str(&format!("{gutter}|\n"));
+        report.push_str(&format!(
+            "{} | {source}\n",
+            line_number.map_or(String::new(), |x| x.to_string())
+        ));
+        if let Some(span) = &detail.span {
+            let width = source
+                .get(span.clone())
+                .map_or(1, |x| x.chars().count().max(1));
+            report.push_str(&format!(
+                "{gutter}| {}{} expected {}\n",
+                " ".repeat(column),
+                "^".repeat(width),
+                detail.expected
+            ));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_error_formatting() {
+        let err = ScheduleError::InvalidPriority(FieldError::new(
+            Field::Priority,
+            "25",
+            "priority in range [0, 20]",
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Expected priority in range [0, 20], got 25"
+        );
+        assert_eq!(err.field(), Field::Priority);
+    }
+
+    #[test]
+    fn report_points_at_span() {
+        let source = "20240101 00:00 - 25 normalscan common";
+        let err = ScheduleError::InvalidPriority(FieldError::new(
+            Field::Priority,
+            "25",
+            "priority in range [0, 20]",
+        ))
+        .with_span(17..19)
+        .with_line(12)
+        .with_path(Path::new("sas.scd"));
+        assert_eq!(
+            err.report(source),
+            "error: Expected priority in range [0, 20], got 25\n\
+            \x20 --> sas.scd:12:18\n\
+            \x20  |\n\
+            12 | 20240101 00:00 - 25 normalscan common\n\
+            \x20  |                  ^^ expected priority in range [0, 20]\n"
+        );
+    }
+}
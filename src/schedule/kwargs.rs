@@ -0,0 +1,164 @@
+//! Keyword arguments passed to an experiment by a schedule line.
+//!
+//! Kwargs follow the experiment and scheduling mode, separated by whitespace. Each is a flag
+//! (`--embargo`), a key and value (`freq=10500` or `--freq=10500`), or a bare word. Values may be
+//! quoted to include whitespace, as in `comment="two words"`. The text of each kwarg is kept as
+//! written so that lines are formatted exactly as they were read.
+
+use super::{Field, FieldError, ScheduleError};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// A single kwarg of a schedule line.
+#[derive(Debug, Clone, Default, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Kwarg {
+    /// Name of the kwarg, without leading dashes.
+    pub key: String,
+    /// Value of the kwarg with any quotes removed, or `None` for flags.
+    pub value: Option<String>,
+    /// The kwarg as written.
+    text: String,
+}
+impl Kwarg {
+    /// Reads a single kwarg, which must not contain unquoted whitespace.
+    fn parse(text: &str) -> Kwarg {
+        let (key, value) = match text.split_once('=') {
+            Some((key, value)) => (key, Some(unquote(value))),
+            None => (text, None),
+        };
+        Kwarg {
+            key: unquote(key.trim_start_matches('-')),
+            value,
+            text: text.to_string(),
+        }
+    }
+}
+impl Display for Kwarg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The kwargs of a schedule line, in the order they were written.
+#[derive(Debug, Clone, Default, Ord, PartialOrd, Eq, PartialEq)]
+pub struct Kwargs(Vec<Kwarg>);
+impl Kwargs {
+    /// Reads whitespace-separated kwargs. Blank text gives no kwargs.
+    pub fn parse(text: &str) -> Result<Kwargs, ScheduleError> {
+        let mut kwargs = vec![];
+        for span in split(text)? {
+            kwargs.push(Kwarg::parse(&text[span]));
+        }
+        Ok(Kwargs(kwargs))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Kwarg> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+impl Display for Kwargs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, kwarg) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{kwarg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `text` on whitespace that is not quoted, giving the byte range of each piece.
+fn split(text: &str) -> Result<Vec<Range<usize>>, ScheduleError> {
+    let mut spans = vec![];
+    let mut start = None;
+    let mut quote: Option<(char, usize)> = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some((q, _)), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => {
+                quote = Some((c, i));
+                start.get_or_insert(i);
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    spans.push(s..i);
+                }
+            }
+            (None, _) => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    if let Some((q, i)) = quote {
+        return Err(ScheduleError::InvalidKwargs(FieldError::new(
+            Field::Kwargs,
+            &text[i..],
+            &format!("closing {q}"),
+        ))
+        .with_span(i..text.len()));
+    }
+    if let Some(s) = start {
+        spans.push(s..text.len());
+    }
+    Ok(spans)
+}
+
+/// Removes quotes, e.g. `"two words"` becomes `two words`.
+fn unquote(text: &str) -> String {
+    let mut unquoted = String::new();
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            _ => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_kwargs() {
+        let text = "--embargo freq=10500 --comment=\"two words\" 'a b'=c bare";
+        let kwargs = Kwargs::parse(text).unwrap();
+        assert_eq!(
+            kwargs
+                .iter()
+                .map(|x| (x.key.as_str(), x.value.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("embargo", None),
+                ("freq", Some("10500")),
+                ("comment", Some("two words")),
+                ("a b", Some("c")),
+                ("bare", None),
+            ]
+        );
+        assert_eq!(kwargs.to_string(), text);
+
+        assert!(Kwargs::parse("").unwrap().is_empty());
+        assert!(Kwargs::parse("  \t ").unwrap().is_empty());
+        assert_eq!(
+            Kwargs::parse("  a=1   b=2 ").unwrap().to_string(),
+            "a=1 b=2"
+        );
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let err = Kwargs::parse("a=1 comment=\"oops").unwrap_err();
+        assert_eq!(err.field(), Field::Kwargs);
+        assert_eq!(err.to_string(), "Expected closing \", got \"oops");
+        assert_eq!(err.detail().span, Some(12..17));
+    }
+}
@@ -0,0 +1,190 @@
+//! Searching a schedule for lines matching a query.
+//!
+//! A query is made of whitespace-separated terms, all of which a line must match:
+//!
+//! - `exp:<text>`, or just `<text>`: the experiment name contains the text
+//! - `mode:<mode>`: the scheduling mode starts with the given text, e.g. `mode:spec`
+//! - `prio:<n>` or `prio:<low>-<high>`: the priority is in the range, where either end may be
+//!   left out
+//! - `date:<YYYYMMDD>` or `date:<YYYYMMDD>-<YYYYMMDD>`: the line is scheduled at some point during
+//!   the days given, where either end may be left out
+//! - `kwarg:<text>`: one of the kwargs contains the text
+//!
+//! Text is matched ignoring case.
+
+use crate::schedule::{parse_date, ScheduleLine};
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Expected {expected}, got {token}")]
+pub struct QueryError {
+    pub token: String,
+    pub expected: String,
+}
+impl QueryError {
+    fn new(token: &str, expected: &str) -> QueryError {
+        QueryError {
+            token: token.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Experiment(String),
+    Mode(String),
+    Priority(RangeInclusive<u8>),
+    /// Lines scheduled at any time from the start up to, but not including, the end.
+    Date(Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    Kwarg(String),
+}
+impl Term {
+    fn parse(token: &str) -> Result<Term, QueryError> {
+        let Some((key, value)) = token.split_once(':') else {
+            return Ok(Term::Experiment(token.to_lowercase()));
+        };
+        match key {
+            "exp" => Ok(Term::Experiment(value.to_lowercase())),
+            "mode" => Ok(Term::Mode(value.to_lowercase())),
+            "kwarg" => Ok(Term::Kwarg(value.to_lowercase())),
+            "prio" => {
+                let invalid = || QueryError::new(token, "prio:<n> or prio:<low>-<high>");
+                let (low, high) = value.split_once('-').unwrap_or((value, value));
+                let low = if low.is_empty() {
+                    0
+                } else {
+                    low.parse().map_err(|_| invalid())?
+                };
+                let high = if high.is_empty() {
+                    u8::MAX
+                } else {
+                    high.parse().map_err(|_| invalid())?
+                };
+                Ok(Term::Priority(low..=high))
+            }
+            "date" => {
+                let day = |x: &str| -> Result<Option<DateTime<Utc>>, QueryError> {
+                    if x.is_empty() {
+                        return Ok(None);
+                    }
+                    parse_date(x)
+                        .map(|x| Some(x.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+                        .map_err(|_| {
+                            QueryError::new(token, "date:<YYYYMMDD> or date:<YYYYMMDD>-<YYYYMMDD>")
+                        })
+                };
+                let (start, end) = value.split_once('-').unwrap_or((value, value));
+                // The end day is included in full
+                Ok(Term::Date(
+                    day(start)?,
+                    day(end)?.map(|x| x + Duration::days(1)),
+                ))
+            }
+            _ => Err(QueryError::new(
+                token,
+                "a search term such as exp:, mode:, prio:, date: or kwarg:",
+            )),
+        }
+    }
+
+    fn matches(&self, line: &ScheduleLine) -> bool {
+        match self {
+            Term::Experiment(x) => line.experiment.to_lowercase().contains(x),
+            Term::Mode(x) => line.scheduling_mode.to_string().starts_with(x.as_str()),
+            Term::Priority(range) => range.contains(&line.priority),
+            Term::Date(start, end) => {
+                let starts_in_time = end.is_none_or(|end| line.timestamp < end);
+                let ends_in_time = match (start, line.end_time()) {
+                    (Some(start), Some(line_end)) => line_end > *start,
+                    _ => true,
+                };
+                starts_in_time && ends_in_time
+            }
+            Term::Kwarg(x) => line
+                .kwargs
+                .iter()
+                .any(|kwarg| kwarg.to_string().to_lowercase().contains(x)),
+        }
+    }
+}
+
+/// A query for finding schedule lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    text: String,
+    terms: Vec<Term>,
+}
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        Ok(Query {
+            text: text.trim().to_string(),
+            terms: text
+                .split_whitespace()
+                .map(Term::parse)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Whether `line` matches every term of the query.
+    pub fn matches(&self, line: &ScheduleLine) -> bool {
+        self.terms.iter().all(|x| x.matches(line))
+    }
+}
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_lines() {
+        let lines: Vec<ScheduleLine> = [
+            "20240101 00:00 - 0 normalscan common",
+            "20240301 12:00 120 10 twofsound discretionary freq1=10500",
+            "20240305 00:00 2880 15 full_fov special --embargo",
+        ]
+        .iter()
+        .map(|x| ScheduleLine::try_from(&x.to_string()).unwrap())
+        .collect();
+        let find = |query: &str| -> Vec<usize> {
+            let query = Query::parse(query).unwrap();
+            (0..lines.len())
+                .filter(|&i| query.matches(&lines[i]))
+                .collect()
+        };
+
+        assert_eq!(find(""), vec![0, 1, 2]);
+        assert_eq!(find("SCAN"), vec![0]);
+        assert_eq!(find("exp:f"), vec![1, 2]);
+        assert_eq!(find("mode:spec"), vec![2]);
+        assert_eq!(find("prio:10-"), vec![1, 2]);
+        assert_eq!(find("prio:-10"), vec![0, 1]);
+        assert_eq!(find("prio:15"), vec![2]);
+        assert_eq!(find("kwarg:10500"), vec![1]);
+        assert_eq!(find("kwarg:embargo exp:full"), vec![2]);
+
+        // Lines scheduled at any point in the days given, including infinite lines
+        assert_eq!(find("date:20240301"), vec![0, 1]);
+        assert_eq!(find("date:20240306-"), vec![0, 2]);
+        assert_eq!(find("date:-20231231"), Vec::<usize>::new());
+        assert_eq!(find("date:20240302-20240304"), vec![0]);
+    }
+
+    #[test]
+    fn invalid_queries() {
+        assert_eq!(
+            Query::parse("prio:high").unwrap_err().to_string(),
+            "Expected prio:<n> or prio:<low>-<high>, got prio:high"
+        );
+        assert!(Query::parse("date:2024").is_err());
+        assert!(Query::parse("when:now").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Rules for repeating a schedule line, e.g. every Tuesday for six weeks.
+//!
+//! A rule is a frequency followed by `key=value` options, separated by whitespace:
+//!
+//! - `daily`, `weekly` or `monthly`: how often the line repeats
+//! - `every=<n>`: repeat every `n` days, weeks or months rather than every one
+//! - `count=<n>`: the number of lines, including the first, up to 1000
+//! - `until=<YYYYMMDD>`: the last day a line may start on
+//!
+//! Exactly one of `count` and `until` must be given. Monthly lines start on the same day of the
+//! month as the first line, or on the last day of months that are too short.
+
+use super::{parse_date, Field, FieldError, ScheduleError, ScheduleLine};
+use chrono::{Duration, Months, NaiveDate};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Most lines a single rule may make, to catch mistakes like `daily until=20991231`.
+const MAX_OCCURRENCES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+impl Display for Frequency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+        })
+    }
+}
+
+/// When a rule stops repeating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Count(usize),
+    Until(NaiveDate),
+}
+
+/// A rule for repeating a schedule line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub every: u32,
+    pub limit: Limit,
+}
+impl Recurrence {
+    pub fn parse(text: &str) -> Result<Recurrence, ScheduleError> {
+        let invalid = |token: &str, expected: &str| {
+            ScheduleError::InvalidRecurrence(FieldError::new(Field::Recurrence, token, expected))
+        };
+        let mut tokens = text.split_whitespace();
+        let frequency = match tokens.next() {
+            Some("daily") => Frequency::Daily,
+            Some("weekly") => Frequency::Weekly,
+            Some("monthly") => Frequency::Monthly,
+            token => return Err(invalid(token.unwrap_or(""), "daily, weekly or monthly")),
+        };
+        let mut every = 1;
+        let mut limit = None;
+        for token in tokens {
+            match token.split_once('=').map(|(key, _)| key) {
+                Some("every") => {
+                    every = positive(token)
+                        .ok_or_else(|| invalid(token, "every=<n> with n at least 1"))?
+                }
+                Some("count") if limit.is_none() => {
+                    let count = positive(token)
+                        .filter(|&x| x <= MAX_OCCURRENCES)
+                        .ok_or_else(|| {
+                            invalid(
+                                token,
+                                &format!("count=<n> with n from 1 to {MAX_OCCURRENCES}"),
+                            )
+                        })?;
+                    limit = Some(Limit::Count(count));
+                }
+                Some("until") if limit.is_none() => {
+                    let date = parse_date(&token["until=".len()..])
+                        .map_err(|_| invalid(token, "until=<YYYYMMDD>"))?;
+                    limit = Some(Limit::Until(date));
+                }
+                Some("count" | "until") => {
+                    return Err(invalid(token, "only one of count or until"))
+                }
+                _ => return Err(invalid(token, "every=<n>, count=<n> or until=<YYYYMMDD>")),
+            }
+        }
+        let Some(limit) = limit else {
+            return Err(invalid(text.trim(), "count=<n> or until=<YYYYMMDD>"));
+        };
+        Ok(Recurrence {
+            frequency,
+            every,
+            limit,
+        })
+    }
+
+    /// Repeats `line` following the rule, starting with `line` itself.
+    pub fn expand(&self, line: &ScheduleLine) -> Result<Vec<ScheduleLine>, ScheduleError> {
+        let invalid = |expected: &str| {
+            ScheduleError::InvalidRecurrence(FieldError::new(
+                Field::Recurrence,
+                &self.to_string(),
+                expected,
+            ))
+        };
+        let mut lines = vec![];
+        for i in 0u32.. {
+            if matches!(self.limit, Limit::Count(count) if lines.len() >= count) {
+                break;
+            }
+            let timestamp = i
+                .checked_mul(self.every)
+                .and_then(|step| match self.frequency {
+                    Frequency::Daily => line
+                        .timestamp
+                        .checked_add_signed(Duration::days(step.into())),
+                    Frequency::Weekly => line
+                        .timestamp
+                        .checked_add_signed(Duration::weeks(step.into())),
+                    Frequency::Monthly => line.timestamp.checked_add_months(Months::new(step)),
+                })
+                .ok_or_else(|| invalid("a rule ending before the last representable date"))?;
+            if matches!(self.limit, Limit::Until(date) if timestamp.date_naive() > date) {
+                break;
+            }
+            if lines.len() >= MAX_OCCURRENCES {
+                return Err(invalid(&format!(
+                    "a rule making at most {MAX_OCCURRENCES} lines"
+                )));
+            }
+            let mut repeat = line.clone();
+            repeat.timestamp = timestamp;
+            lines.push(repeat);
+        }
+        Ok(lines)
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.frequency)?;
+        if self.every != 1 {
+            write!(f, " every={}", self.every)?;
+        }
+        match self.limit {
+            Limit::Count(count) => write!(f, " count={count}"),
+            Limit::Until(date) => write!(f, " until={}", date.format("%Y%m%d")),
+        }
+    }
+}
+
+/// The number in a `key=<n>` option, if it is a valid number of at least 1.
+fn positive<T: FromStr + PartialOrd + From<u8>>(token: &str) -> Option<T> {
+    let (_, value) = token.split_once('=')?;
+    value.parse().ok().filter(|x| *x >= T::from(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(rule: &str, line: &str) -> Vec<String> {
+        let line = ScheduleLine::try_from(&line.to_string()).unwrap();
+        Recurrence::parse(rule)
+            .unwrap()
+            .expand(&line)
+            .unwrap()
+            .iter()
+            .map(|x| x.timestamp.format("%Y%m%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn expand_rules() {
+        let tuesday = "20250107 14:00 120 15 twofsound special";
+        assert_eq!(
+            starts("weekly count=3", tuesday),
+            vec!["20250107 14:00", "20250114 14:00", "20250121 14:00"]
+        );
+        assert_eq!(
+            starts("daily every=2 until=20250111", tuesday),
+            vec!["20250107 14:00", "20250109 14:00", "20250111 14:00"]
+        );
+        // Short months clamp to their last day without moving later months
+        assert_eq!(
+            starts(
+                "monthly count=4",
+                "20250131 00:00 2880 15 twofsound special"
+            ),
+            vec![
+                "20250131 00:00",
+                "20250228 00:00",
+                "20250331 00:00",
+                "20250430 00:00"
+            ]
+        );
+        assert!(starts("weekly until=20250101", tuesday).is_empty());
+
+        let line = ScheduleLine::try_from(&tuesday.to_string()).unwrap();
+        let lines = Recurrence::parse("weekly count=2")
+            .unwrap()
+            .expand(&line)
+            .unwrap();
+        assert_eq!(lines[1].format(), "20250114 14:00 120 15 twofsound special");
+        assert!(Recurrence::parse("daily until=20991231")
+            .unwrap()
+            .expand(&line)
+            .is_err());
+
+        // Lines too far in the future to represent are an error rather than the end of the rule
+        assert_eq!(
+            Recurrence::parse("monthly every=4000000 count=2")
+                .unwrap()
+                .expand(&line)
+                .unwrap_err()
+                .to_string(),
+            "Expected a rule ending before the last representable date, \
+            got monthly every=4000000 count=2"
+        );
+        assert!(Recurrence::parse("weekly every=4000000000 count=3")
+            .unwrap()
+            .expand(&line)
+            .is_err());
+        assert_eq!(starts("daily every=4000000000 count=1", tuesday).len(), 1);
+    }
+
+    #[test]
+    fn parse_rules() {
+        let rule = Recurrence::parse(" monthly  every=3 until=20251231 ").unwrap();
+        assert_eq!(rule.to_string(), "monthly every=3 until=20251231");
+        assert_eq!(
+            Recurrence::parse("yearly count=2").unwrap_err().to_string(),
+            "Expected daily, weekly or monthly, got yearly"
+        );
+        assert_eq!(
+            Recurrence::parse("weekly").unwrap_err().to_string(),
+            "Expected count=<n> or until=<YYYYMMDD>, got weekly"
+        );
+        assert!(Recurrence::parse("weekly count=0").is_err());
+        assert_eq!(
+            Recurrence::parse("daily count=5000")
+                .unwrap_err()
+                .to_string(),
+            "Expected count=<n> with n from 1 to 1000, got count=5000"
+        );
+        assert!(Recurrence::parse("daily count=1000").is_ok());
+        // Too large for every, rather than wrapping around to 0
+        assert_eq!(
+            Recurrence::parse("daily every=4294967296 count=2")
+                .unwrap_err()
+                .to_string(),
+            "Expected every=<n> with n at least 1, got every=4294967296"
+        );
+        assert!(Recurrence::parse("weekly count=2 until=20250101").is_err());
+        assert!(Recurrence::parse("weekly until=2025").is_err());
+        assert!(Recurrence::parse("weekly count=2 on=tuesday").is_err());
+        assert_eq!(
+            Recurrence::parse("").unwrap_err().field(),
+            Field::Recurrence
+        );
+    }
+}
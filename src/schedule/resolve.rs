@@ -0,0 +1,162 @@
+//! Resolution of a schedule into the lines that will actually run.
+//!
+//! Borealis decides what to run at any instant by looking at every line whose window covers that
+//! instant and picking the one with the highest priority. Ties are broken in favour of the line
+//! that started most recently, so a newer infinite line replaces an older one as the baseline,
+//! and a finite line preempts the baseline for its duration before handing control back.
+
+use crate::schedule::ScheduleLine;
+use chrono::{DateTime, Utc};
+
+/// A span of time during which a single schedule line is in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment<'a> {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub line: &'a ScheduleLine,
+}
+
+/// Returns whether `line` is scheduled (whether or not it is preempted) at `instant`.
+pub fn covers(line: &ScheduleLine, instant: DateTime<Utc>) -> bool {
+    line.timestamp <= instant && line.end_time().is_none_or(|end| instant < end)
+}
+
+/// Returns the line that Borealis will run at `instant`, if any.
+pub fn active_at(lines: &[ScheduleLine], instant: DateTime<Utc>) -> Option<&ScheduleLine> {
+    lines
+        .iter()
+        .filter(|line| covers(line, instant))
+        .max_by(|a, b| {
+            (a.priority, a.timestamp)
+                .cmp(&(b.priority, b.timestamp))
+                .then_with(|| a.cmp(b))
+        })
+}
+
+/// Computes the non-overlapping segments of `[start, end)` during which each line runs.
+///
+/// Segments are returned in chronological order, with adjacent spans of the same line merged.
+/// Periods where no line is scheduled are left as gaps between segments.
+pub fn resolve(
+    lines: &[ScheduleLine],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<Segment<'_>> {
+    if start >= end {
+        return vec![];
+    }
+
+    // The set of covering lines can only change where a line starts or ends
+    let mut breakpoints = vec![start, end];
+    for line in lines.iter() {
+        breakpoints.push(line.timestamp);
+        if let Some(line_end) = line.end_time() {
+            breakpoints.push(line_end);
+        }
+    }
+    breakpoints.retain(|t| *t >= start && *t <= end);
+    breakpoints.sort();
+    breakpoints.dedup();
+
+    let mut segments: Vec<Segment> = vec![];
+    for window in breakpoints.windows(2) {
+        let Some(line) = active_at(lines, window[0]) else {
+            continue;
+        };
+        match segments.last_mut() {
+            Some(last) if last.end == window[0] && std::ptr::eq(last.line, line) => {
+                last.end = window[1];
+            }
+            _ => segments.push(Segment {
+                start: window[0],
+                end: window[1],
+                line,
+            }),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use std::error::Error;
+
+    fn time(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y%m%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn line(s: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn later_infinite_line_replaces_baseline() {
+        let lines = vec![
+            line("20240101 00:00 - 0 normalscan common"),
+            line("20240105 00:00 - 0 twofsound common"),
+        ];
+        let segments = resolve(&lines, time("20240101 00:00"), time("20240110 00:00"));
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].line, &lines[0]);
+        assert_eq!(segments[0].end, time("20240105 00:00"));
+        assert_eq!(segments[1].line, &lines[1]);
+        assert_eq!(segments[1].end, time("20240110 00:00"));
+    }
+
+    #[test]
+    fn higher_priority_preempts_and_hands_back() -> Result<(), Box<dyn Error>> {
+        let lines = vec![
+            line("20240101 00:00 - 0 normalscan common"),
+            line("20240102 00:00 120 10 full_fov special"),
+            line("20240102 01:00 60 5 twofsound discretionary"),
+        ];
+        let segments = resolve(&lines, time("20240102 00:00"), time("20240102 06:00"));
+        let names: Vec<&str> = segments
+            .iter()
+            .map(|s| s.line.experiment.as_str())
+            .collect();
+        assert_eq!(names, vec!["full_fov", "normalscan"]);
+        assert_eq!(segments[0].end, time("20240102 02:00"));
+        assert_eq!(
+            active_at(&lines, time("20240102 01:30")).ok_or("nothing active")?,
+            &lines[1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn same_priority_later_start_wins() {
+        let lines = vec![
+            line("20240101 00:00 240 5 normalscan special"),
+            line("20240101 01:00 60 5 twofsound special"),
+        ];
+        let segments = resolve(&lines, time("20240101 00:00"), time("20240101 06:00"));
+        let spans: Vec<(&str, DateTime<Utc>, DateTime<Utc>)> = segments
+            .iter()
+            .map(|s| (s.line.experiment.as_str(), s.start, s.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("normalscan", time("20240101 00:00"), time("20240101 01:00")),
+                ("twofsound", time("20240101 01:00"), time("20240101 02:00")),
+                ("normalscan", time("20240101 02:00"), time("20240101 04:00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_are_left_empty() {
+        let lines = vec![line("20240101 01:00 60 0 normalscan common")];
+        let segments = resolve(&lines, time("20240101 00:00"), time("20240101 03:00"));
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].start, time("20240101 01:00"));
+        assert_eq!(segments[0].end, time("20240101 02:00"));
+        assert!(active_at(&lines, time("20240101 02:00")).is_none());
+        assert!(resolve(&lines, time("20240101 03:00"), time("20240101 00:00")).is_empty());
+    }
+}
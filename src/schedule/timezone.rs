@@ -0,0 +1,229 @@
+//! Time zones that start times can be entered in, which are converted to UTC for the schedule.
+//!
+//! A zone is one of:
+//!
+//! - `UTC`, or nothing: times are already in UTC
+//! - `local`: the civil time zone of the site, from [`SITES`]
+//! - `solar`: local mean solar time at the site, which is UTC shifted by four minutes for every
+//!   degree of longitude, rounded to the minute
+//! - the name of any IANA time zone, e.g. `America/Regina`
+//!
+//! Names are matched ignoring case.
+
+use super::{Field, FieldError, ScheduleError};
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt::{Display, Formatter};
+
+/// A radar site, with what is needed to work out its local times.
+pub struct Site {
+    pub id: &'static str,
+    pub timezone: Tz,
+    /// Degrees east of Greenwich, negative to the west.
+    pub longitude: f64,
+}
+
+/// Every site with a known time zone.
+pub const SITES: &[Site] = &[
+    Site {
+        id: "cly",
+        timezone: Tz::America__Iqaluit,
+        longitude: -68.50,
+    },
+    Site {
+        id: "inv",
+        timezone: Tz::America__Inuvik,
+        longitude: -133.77,
+    },
+    Site {
+        id: "pgr",
+        timezone: Tz::America__Vancouver,
+        longitude: -122.59,
+    },
+    Site {
+        id: "rkn",
+        timezone: Tz::America__Rankin_Inlet,
+        longitude: -93.11,
+    },
+    Site {
+        id: "sas",
+        timezone: Tz::America__Regina,
+        longitude: -106.53,
+    },
+];
+
+/// Looks up a site by its ID, ignoring case.
+pub fn site(id: &str) -> Option<&'static Site> {
+    SITES.iter().find(|x| x.id.eq_ignore_ascii_case(id))
+}
+
+/// A time zone that a start time was entered in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Utc,
+    Named(Tz),
+    /// Local mean solar time of the site with the given ID.
+    Solar(&'static str),
+}
+impl Zone {
+    /// Parses a zone for the site with ID `site`, which `local` and `solar` refer to.
+    pub fn parse(text: &str, site_id: &str) -> Result<Zone, ScheduleError> {
+        let text = text.trim();
+        let unknown_site = || {
+            ScheduleError::InvalidTimezone(FieldError::new(
+                Field::Timezone,
+                text,
+                &format!("a site with a known time zone, not {site_id}"),
+            ))
+        };
+        match text.to_lowercase().as_str() {
+            "" | "utc" => Ok(Zone::Utc),
+            "local" => site(site_id)
+                .map(|x| Zone::Named(x.timezone))
+                .ok_or_else(unknown_site),
+            "solar" => site(site_id)
+                .map(|x| Zone::Solar(x.id))
+                .ok_or_else(unknown_site),
+            _ => chrono_tz::TZ_VARIANTS
+                .iter()
+                .find(|x| x.name().eq_ignore_ascii_case(text))
+                .map(|&x| Zone::Named(x))
+                .ok_or_else(|| {
+                    ScheduleError::InvalidTimezone(FieldError::new(
+                        Field::Timezone,
+                        text,
+                        "UTC, local, solar or a time zone name like America/Regina",
+                    ))
+                }),
+        }
+    }
+
+    /// The UTC time of `time` in this zone. Local times that are skipped or happen twice when
+    /// clocks change are rejected rather than guessed at.
+    pub fn to_utc(self, time: NaiveDateTime) -> Result<DateTime<Utc>, ScheduleError> {
+        let result = match self {
+            Zone::Utc => return Ok(time.and_utc()),
+            Zone::Named(tz) => tz.from_local_datetime(&time).map(|x| x.to_utc()),
+            Zone::Solar(id) => solar_offset(id)
+                .from_local_datetime(&time)
+                .map(|x| x.to_utc()),
+        };
+        match result {
+            LocalResult::Single(x) => Ok(x),
+            LocalResult::Ambiguous(..) | LocalResult::None => {
+                Err(ScheduleError::InvalidTime(FieldError::new(
+                    Field::Start,
+                    &time.format("%Y%m%d %H:%M").to_string(),
+                    &format!("a time that happens exactly once in {self}"),
+                )))
+            }
+        }
+    }
+
+    /// The local time in this zone at `time`.
+    pub fn local_time(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Utc => time.naive_utc(),
+            Zone::Named(tz) => time.with_timezone(tz).naive_local(),
+            Zone::Solar(id) => time.with_timezone(&solar_offset(id)).naive_local(),
+        }
+    }
+
+    /// `time` in this zone, as text with the zone's abbreviation, e.g. `20250101 12:00 CST`.
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Zone::Utc => time.format("%Y%m%d %H:%M UTC").to_string(),
+            Zone::Named(tz) => time.with_timezone(tz).format("%Y%m%d %H:%M %Z").to_string(),
+            Zone::Solar(_) => format!("{} solar", self.local_time(time).format("%Y%m%d %H:%M")),
+        }
+    }
+}
+impl Display for Zone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Utc => f.write_str("UTC"),
+            Zone::Named(tz) => f.write_str(tz.name()),
+            Zone::Solar(id) => write!(f, "solar time at {id}"),
+        }
+    }
+}
+
+/// Offset of local mean solar time from UTC at the site with ID `id`, to the nearest minute so
+/// that converted times stay on whole minutes like the rest of the schedule.
+fn solar_offset(id: &str) -> FixedOffset {
+    let longitude = site(id).map_or(0.0, |x| x.longitude);
+    FixedOffset::east_opt((longitude * 4.0).round() as i32 * 60).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::parse_timestamp;
+
+    fn local(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y%m%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn convert_to_utc() {
+        let utc = |zone: &str, site: &str, time: &str| {
+            Zone::parse(zone, site)
+                .unwrap()
+                .to_utc(local(time))
+                .map(|x| x.format("%Y%m%d %H:%M").to_string())
+        };
+        assert_eq!(utc("", "sas", "20250101 12:00").unwrap(), "20250101 12:00");
+        // Saskatchewan stays on CST all year
+        assert_eq!(
+            utc("local", "sas", "20250701 12:00").unwrap(),
+            "20250701 18:00"
+        );
+        assert_eq!(
+            utc("local", "pgr", "20250701 12:00").unwrap(),
+            "20250701 19:00"
+        );
+        assert_eq!(
+            utc("america/toronto", "sas", "20250101 12:00").unwrap(),
+            "20250101 17:00"
+        );
+        // 106.53 degrees west is 7 hours and 6 minutes behind UTC, to the minute
+        assert_eq!(
+            utc("solar", "sas", "20250101 12:00").unwrap(),
+            "20250101 19:06"
+        );
+
+        // Clocks in Vancouver skip from 02:00 to 03:00 and repeat 01:00 to 02:00
+        assert_eq!(
+            utc("local", "pgr", "20250309 02:30").unwrap_err().field(),
+            Field::Start
+        );
+        assert!(utc("local", "pgr", "20251102 01:30").is_err());
+    }
+
+    #[test]
+    fn parse_zones() {
+        assert_eq!(Zone::parse(" UTC ", "xyz").unwrap(), Zone::Utc);
+        assert_eq!(
+            Zone::parse("Local", "SAS").unwrap(),
+            Zone::Named(Tz::America__Regina)
+        );
+        assert_eq!(
+            Zone::parse("local", "xyz").unwrap_err().to_string(),
+            "Expected a site with a known time zone, not xyz, got local"
+        );
+        assert_eq!(
+            Zone::parse("Mars/Olympus", "sas").unwrap_err().field(),
+            Field::Timezone
+        );
+
+        let time = parse_timestamp("20250101 18:00").unwrap();
+        assert_eq!(
+            Zone::parse("local", "sas").unwrap().format(time),
+            "20250101 12:00 CST"
+        );
+        assert_eq!(
+            Zone::parse("solar", "sas").unwrap().format(time),
+            "20250101 10:54 solar"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Points in time written the ways people tend to write them, for start and end times.
+//!
+//! A time can be written as:
+//!
+//! - `now`
+//! - `+<n><unit>`, with units `m`, `h`, `d` and `w`, which can be combined as in `+1h30m`: that
+//!   long after the start of the line for an end time, or after now otherwise
+//! - `HH:MM`: the next time it is that time of day, after the start of the line for an end time,
+//!   or after now otherwise
+//! - `today` or `tomorrow`, optionally followed by `HH:MM`
+//! - `YYYYMMDD HH:MM` or just `YYYYMMDD`, as in schedule files
+//! - `YYYY-MM-DDTHH:MM`, optionally with seconds and ending in `Z` or an offset like `-06:00`
+//!
+//! Dates and times of day are in the time zone given, unless written with an offset.
+
+use super::timezone::Zone;
+use super::{parse_date, parse_time, Field, FieldError, ScheduleError};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+const EXPECTED: &str =
+    "now, +<n>m/h/d/w, HH:MM, today or tomorrow HH:MM, YYYYMMDD HH:MM or YYYY-MM-DDTHH:MMZ";
+
+/// Parses the start of a line.
+pub fn parse_start(
+    text: &str,
+    zone: Zone,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    parse(text, Field::Start, zone, now, now)
+}
+
+/// Parses the end of a line starting at `start`, which relative times are counted from.
+pub fn parse_end(
+    text: &str,
+    zone: Zone,
+    start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    parse(text, Field::End, zone, now, start)
+}
+
+/// Parses `text` as a time for `field`, with relative times counted from `base`.
+fn parse(
+    text: &str,
+    field: Field,
+    zone: Zone,
+    now: DateTime<Utc>,
+    base: DateTime<Utc>,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    let text = text.trim();
+    let invalid = || ScheduleError::InvalidDate(FieldError::new(field, text, EXPECTED));
+    // Local times are converted with the error pointing at this field
+    let to_utc = |time: NaiveDateTime| {
+        zone.to_utc(time).map_err(|e| {
+            ScheduleError::InvalidTime(FieldError::new(field, text, &e.detail().expected))
+        })
+    };
+
+    if text.eq_ignore_ascii_case("now") {
+        return Ok(now);
+    }
+    if let Some(offset) = text.strip_prefix('+') {
+        return parse_offset(offset).map(|x| base + x).ok_or_else(invalid);
+    }
+    if let Ok(time) = parse_time(text) {
+        let base_local = zone.local_time(base);
+        let mut day = base_local.date();
+        if day.and_time(time) <= base_local {
+            day = day.succ_opt().ok_or_else(invalid)?;
+        }
+        return to_utc(day.and_time(time));
+    }
+
+    // ISO 8601, with either a `T` or a space between the date and time
+    if text.get(4..5) == Some("-") {
+        let iso = text.replacen(' ', "T", 1);
+        let offset = iso
+            .strip_suffix(['Z', 'z'])
+            .map(|x| format!("{x}+00:00"))
+            .unwrap_or_else(|| iso.clone());
+        for format in ["%Y-%m-%dT%H:%M%:z", "%Y-%m-%dT%H:%M:%S%:z"] {
+            if let Ok(time) = DateTime::parse_from_str(&offset, format) {
+                return Ok(time.to_utc());
+            }
+        }
+        for format in ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(&iso, format) {
+                return to_utc(time);
+            }
+        }
+        return match NaiveDate::parse_from_str(&iso, "%Y-%m-%d") {
+            Ok(date) => to_utc(date.and_time(NaiveTime::MIN)),
+            Err(_) => Err(invalid()),
+        };
+    }
+
+    let (day, time) = match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [day] => (*day, NaiveTime::MIN),
+        [day, time] => (*day, parse_time(time).map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    };
+    let today = zone.local_time(now).date();
+    let date = match day.to_lowercase().as_str() {
+        "today" => today,
+        "tomorrow" => today.succ_opt().ok_or_else(invalid)?,
+        _ => parse_date(day).map_err(|_| invalid())?,
+    };
+    to_utc(date.and_time(time))
+}
+
+/// Parses the length of time in a relative time like `+1h30m`, without the `+`.
+fn parse_offset(text: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let n: i64 = rest[..digits].parse().ok()?;
+        let unit = match rest[digits..].chars().next()? {
+            'm' => Duration::try_minutes(n)?,
+            'h' => Duration::try_hours(n)?,
+            'd' => Duration::try_days(n)?,
+            'w' => Duration::try_weeks(n)?,
+            _ => return None,
+        };
+        total = total.checked_add(&unit)?;
+        rest = &rest[digits + 1..];
+    }
+    (!text.is_empty()).then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::parse_timestamp;
+    use chrono_tz::Tz;
+
+    fn start(text: &str, zone: Zone) -> Result<String, ScheduleError> {
+        let now = parse_timestamp("20250301 17:20").unwrap();
+        parse_start(text, zone, now).map(|x| x.format("%Y%m%d %H:%M").to_string())
+    }
+
+    #[test]
+    fn parse_starts() {
+        let utc = Zone::Utc;
+        assert_eq!(start("now", utc).unwrap(), "20250301 17:20");
+        assert_eq!(start("+2h", utc).unwrap(), "20250301 19:20");
+        assert_eq!(start("+1d2h30m", utc).unwrap(), "20250302 19:50");
+        assert_eq!(start("18:00", utc).unwrap(), "20250301 18:00");
+        assert_eq!(start("17:00", utc).unwrap(), "20250302 17:00");
+        assert_eq!(start("tomorrow 18:00", utc).unwrap(), "20250302 18:00");
+        assert_eq!(start("Today", utc).unwrap(), "20250301 00:00");
+        assert_eq!(start("20250401 06:30", utc).unwrap(), "20250401 06:30");
+        assert_eq!(start("20250401", utc).unwrap(), "20250401 00:00");
+        assert_eq!(start("2025-03-01T12:00Z", utc).unwrap(), "20250301 12:00");
+        assert_eq!(start("2025-03-01 12:00", utc).unwrap(), "20250301 12:00");
+        assert_eq!(
+            start("2025-03-01T12:00:00-06:00", utc).unwrap(),
+            "20250301 18:00"
+        );
+
+        for invalid in [
+            "",
+            "later",
+            "+2",
+            "+h",
+            "+2y",
+            "tomorrow 25:00",
+            "2025-03-01T12",
+            "1 2 3",
+        ] {
+            let e = start(invalid, utc).unwrap_err();
+            assert_eq!(e.field(), Field::Start, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parse_in_zone() {
+        // 17:20 UTC is 11:20 in Saskatchewan, on the same day
+        let sas = Zone::Named(Tz::America__Regina);
+        assert_eq!(start("tomorrow 18:00", sas).unwrap(), "20250303 00:00");
+        assert_eq!(start("12:00", sas).unwrap(), "20250301 18:00");
+        assert_eq!(start("20250401 06:30", sas).unwrap(), "20250401 12:30");
+        // An explicit offset wins over the zone
+        assert_eq!(start("2025-03-01T12:00Z", sas).unwrap(), "20250301 12:00");
+    }
+
+    #[test]
+    fn parse_ends() {
+        let now = parse_timestamp("20250301 17:20").unwrap();
+        let line_start = parse_timestamp("20250310 22:00").unwrap();
+        let end = |text: &str| {
+            parse_end(text, Zone::Utc, line_start, now)
+                .map(|x| x.format("%Y%m%d %H:%M").to_string())
+        };
+        assert_eq!(end("+90m").unwrap(), "20250310 23:30");
+        assert_eq!(end("02:00").unwrap(), "20250311 02:00");
+        assert_eq!(end("20250312 00:00").unwrap(), "20250312 00:00");
+        assert_eq!(end("bad").unwrap_err().field(), Field::End);
+    }
+}
@@ -0,0 +1,188 @@
+//! Editing the schedules of several sites at once.
+//!
+//! Each site's schedule is opened in its own [`App`], with its own edits, undo history and
+//! lock. Sites can be marked so that a change made on one site can be copied to all the marked
+//! sites, which is how a change for a campaign across many radars is made.
+
+use crate::app::{App, CurrentScreen};
+use std::error::Error;
+
+/// The schedules open for editing, one of which is shown at a time.
+pub struct Session {
+    pub apps: Vec<App>,
+    /// Index into `apps` of the site being shown.
+    pub current: usize,
+    /// Whether each site is marked to have changes copied to it.
+    pub marked: Vec<bool>,
+    /// Outcome of the last copy, shown beside the site tabs.
+    pub status: Option<String>,
+    /// The next site to check for changes made by someone else before saving.
+    next_check: usize,
+}
+
+impl Session {
+    pub fn new(apps: Vec<App>) -> Session {
+        Session {
+            marked: vec![false; apps.len()],
+            apps,
+            current: 0,
+            status: None,
+            next_check: 0,
+        }
+    }
+
+    pub fn app(&self) -> &App {
+        &self.apps[self.current]
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.apps[self.current]
+    }
+
+    /// Shows site `i`. If the summary of changes was showing, it is shown for the new site.
+    fn switch_to(&mut self, i: usize) {
+        if let CurrentScreen::Exiting = self.app().current_screen {
+            self.app_mut().current_screen = CurrentScreen::Main;
+            self.apps[i].current_screen = CurrentScreen::Exiting;
+        }
+        self.current = i;
+        self.status = None;
+    }
+
+    pub fn next_site(&mut self) {
+        self.switch_to((self.current + 1) % self.apps.len());
+    }
+
+    pub fn previous_site(&mut self) {
+        self.switch_to((self.current + self.apps.len() - 1) % self.apps.len());
+    }
+
+    /// Marks or unmarks the site being shown.
+    pub fn toggle_mark(&mut self) {
+        self.marked[self.current] = !self.marked[self.current];
+    }
+
+    /// Makes the last change to the site being shown on every other marked site. Each copy is
+    /// an edit of that site which can be undone on its own.
+    pub fn copy_last_change(&mut self) {
+        let Some(change) = self.app().last_change.clone() else {
+            self.status = Some("No change to copy".to_string());
+            return;
+        };
+        let from = self.app().site();
+        let mut copied = vec![];
+        let mut skipped = vec![];
+        for i in (0..self.apps.len()).filter(|&i| self.marked[i] && i != self.current) {
+            if self.apps[i].apply_change(&change, Some(&from)) {
+                copied.push(self.apps[i].site());
+            } else {
+                skipped.push(self.apps[i].site());
+            }
+        }
+        self.status = Some(match (copied.is_empty(), skipped.is_empty()) {
+            (true, true) => "No other sites are marked".to_string(),
+            (false, true) => format!("Copied to {}", copied.join(", ")),
+            (true, false) => format!("Could not copy to {}", skipped.join(", ")),
+            (false, false) => format!(
+                "Copied to {}, could not copy to {}",
+                copied.join(", "),
+                skipped.join(", ")
+            ),
+        });
+    }
+
+    /// Checks each site with changes, in turn, for changes made to its schedule file by someone
+    /// else since it was loaded, starting from the first site if `restart` is set and from
+    /// after the last site found otherwise. Returns `true` and shows the merge of the next such
+    /// site if there is one.
+    pub fn check_for_external_changes(&mut self, restart: bool) -> Result<bool, Box<dyn Error>> {
+        if restart {
+            self.next_check = 0;
+        }
+        while self.next_check < self.apps.len() {
+            let i = self.next_check;
+            self.next_check += 1;
+            if self.apps[i].has_changes() && self.apps[i].check_for_external_changes()? {
+                self.app_mut().current_screen = CurrentScreen::Main;
+                self.current = i;
+                self.app_mut().current_screen = CurrentScreen::Merging;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Drops any merges found by `check_for_external_changes`, to go back to editing.
+    pub fn cancel_merges(&mut self) {
+        for app in self.apps.iter_mut() {
+            app.merge = None;
+        }
+    }
+
+    /// Saves every site that has changes, carrying on past any that fail. Returns the site ID
+    /// and error of each failure.
+    pub fn save(&self) -> Vec<(String, Box<dyn Error>)> {
+        self.apps
+            .iter()
+            .filter(|app| app.has_changes())
+            .filter_map(|app| app.save_schedule().err().map(|e| (app.site(), e)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Change;
+    use crate::schedule::ScheduleLine;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn line(text: &str) -> ScheduleLine {
+        ScheduleLine::try_from(&text.to_string()).unwrap()
+    }
+
+    #[test]
+    fn copy_change_to_marked_sites() {
+        let dir = tempdir().unwrap();
+        let baseline = "20200101 00:00 - 0 normalscan common\n";
+        let apps = ["sas", "pgr", "rkn"]
+            .iter()
+            .map(|site| {
+                let path = dir.path().join(format!("{site}.scd"));
+                fs::write(&path, baseline).unwrap();
+                App::new(path, dir.path().into(), false, 0).unwrap()
+            })
+            .collect();
+        let mut session = Session::new(apps);
+        session.marked = vec![true, true, false];
+
+        // The change is made on the marked sites other than the one it was made on
+        let added = line("20250101 00:00 60 10 twofsound special");
+        assert!(session
+            .app_mut()
+            .apply_change(&Change::Add(added.clone()), None));
+        session.copy_last_change();
+        assert_eq!(session.status.as_deref(), Some("Copied to pgr"));
+        assert!(session.apps[1].schedule_list.items.contains(&added));
+        assert!(!session.apps[2].has_changes());
+        assert_eq!(
+            session.apps[1].undo_stack[0].description,
+            "add 20250101 00:00 60 10 twofsound special (from sas)"
+        );
+
+        // Sites without the line being edited are skipped
+        session.marked[2] = true;
+        let edited = line("20250101 00:00 120 10 twofsound special");
+        assert!(session
+            .app_mut()
+            .apply_change(&Change::Edit(added, edited.clone()), None));
+        session.copy_last_change();
+        assert_eq!(
+            session.status.as_deref(),
+            Some("Copied to pgr, could not copy to rkn")
+        );
+        assert!(session.apps[1].schedule_list.items.contains(&edited));
+        assert!(session.apps.iter().all(|x| x.merge.is_none()));
+    }
+}
@@ -1,4 +1,10 @@
 use crate::app::{App, CurrentScreen, CurrentlyEditing};
+use crate::fuzzy::fuzzy_match;
+use crate::schedule::diff::DiffLine;
+use crate::schedule::resolve::{active_at, covers, resolve};
+use crate::schedule::ScheduleLine;
+use crate::session::Session;
+use chrono::{DateTime, Duration, Utc};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::Alignment;
 use ratatui::style::{Color, Modifier, Style, Stylize};
@@ -17,30 +23,107 @@ pub const SELECTION_HEADER_BG: Color = Color::DarkGray;
 pub const KEY_COLOR: Color = Color::LightYellow;
 pub const HINT_COLOR: Color = Color::LightBlue;
 
-pub fn ui(frame: &mut Frame, app: &mut App) {
+/// How far ahead to look when working out how long the current line will keep running.
+const RESOLVE_HORIZON_DAYS: i64 = 365;
+/// Width of the priority label at the start of each timeline lane.
+const LANE_LABEL_WIDTH: usize = 5;
+pub const RUNNING_COLOR: Color = Color::Indexed(10);
+pub const PREEMPTED_COLOR: Color = Color::Gray;
+pub const WARNING_COLOR: Color = Color::Indexed(214);
+
+/// What a single column of a timeline lane shows.
+#[derive(Clone, Copy, PartialEq)]
+enum TimelineCell<'a> {
+    Empty,
+    Running(&'a ScheduleLine),
+    Preempted(&'a ScheduleLine),
+}
+
+pub fn ui(frame: &mut Frame, session: &mut Session) {
+    // The site tabs are only shown when editing more than one site
+    let tabs_height = if session.apps.len() > 1 { 1 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
+            Constraint::Length(tabs_height),
             Constraint::Min(1),
             Constraint::Length(3),
         ])
         .split(frame.area());
 
-    render_header(frame, chunks[0]);
-    render_schedule(frame, app, chunks[1]);
-    render_footer(frame, app, chunks[2]);
+    render_header(frame, session.app(), chunks[0]);
+    if session.apps.len() > 1 {
+        render_tabs(frame, session, chunks[1]);
+    }
+    let app = session.app_mut();
+    if let CurrentScreen::Timeline = app.current_screen {
+        render_timeline(frame, app, chunks[2]);
+    } else {
+        render_schedule(frame, app, chunks[2]);
+    }
+    render_footer(frame, app, chunks[3]);
 
     if app.currently_editing.is_some() {
         render_editor(frame, app);
     }
 
-    if let CurrentScreen::Exiting = app.current_screen {
-        render_exit_screen(frame, app);
+    if let CurrentScreen::Diagnostics = app.current_screen {
+        render_diagnostics(frame, app);
+    }
+
+    if let CurrentScreen::Backups = app.current_screen {
+        render_backups(frame, app);
+    }
+
+    if let CurrentScreen::Archiving = app.current_screen {
+        render_archive(frame, app);
+    }
+
+    if let CurrentScreen::Exiting | CurrentScreen::Merging = app.current_screen {
+        render_exit_screen(frame, session);
+    }
+
+    if let CurrentScreen::Merging = session.app().current_screen {
+        render_merge(frame, session.app());
     }
 }
 
-fn render_header(frame: &mut Frame, area: Rect) {
+fn render_tabs(frame: &mut Frame, session: &Session, area: Rect) {
+    let mut spans = vec![];
+    for (i, app) in session.apps.iter().enumerate() {
+        let mark = if session.marked[i] { "*" } else { "" };
+        let style = if i == session.current {
+            Style::default()
+                .fg(SELECTION_STYLE_FG)
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().fg(TEXT_COLOR)
+        };
+        spans.push(Span::styled(format!(" {}{mark} ", app.site()), style));
+        spans.push(Span::raw(" "));
+    }
+    match &session.status {
+        Some(status) => spans.push(Span::styled(status.clone(), HINT_COLOR)),
+        None => spans.extend([
+            Span::styled("(Tab)", KEY_COLOR),
+            Span::styled(" to switch site / ", HINT_COLOR),
+            Span::styled("(m)", KEY_COLOR),
+            Span::styled(" to mark this site / ", HINT_COLOR),
+            Span::styled("(c)", KEY_COLOR),
+            Span::styled(" to copy the last change to marked (*) sites", HINT_COLOR),
+        ]),
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+fn render_header(frame: &mut Frame, app: &App, area: Rect) {
+    let header_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
     let title_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default());
@@ -51,7 +134,39 @@ fn render_header(frame: &mut Frame, area: Rect) {
     ))
     .block(title_block);
 
-    frame.render_widget(title, area);
+    // Work out what the radar is running right now, and until when
+    let now = Utc::now();
+    let horizon = now + Duration::days(RESOLVE_HORIZON_DAYS);
+    let running_text = match resolve(&app.schedule_list.items, now, horizon).first() {
+        Some(segment) if segment.start == now => {
+            let until = if segment.end == horizon && segment.line.end_time().is_none() {
+                "indefinitely".to_string()
+            } else {
+                format!("until {}", segment.end.format("%Y%m%d %H:%M"))
+            };
+            vec![
+                Span::styled("Running now: ", Style::default().fg(HINT_COLOR)),
+                Span::styled(
+                    format!(
+                        "{} {} ",
+                        segment.line.experiment, segment.line.scheduling_mode
+                    ),
+                    Style::default().fg(TEXT_COLOR),
+                ),
+                Span::styled(until, Style::default().fg(HINT_COLOR)),
+            ]
+        }
+        _ => vec![Span::styled(
+            "Nothing scheduled right now",
+            Style::default().fg(Color::LightRed),
+        )],
+    };
+    let running = Paragraph::new(Line::from(running_text))
+        .alignment(Alignment::Right)
+        .block(Block::default().borders(Borders::ALL));
+
+    frame.render_widget(title, header_chunks[0]);
+    frame.render_widget(running, header_chunks[1]);
 }
 
 fn render_schedule(frame: &mut Frame, app: &mut App, area: Rect) {
@@ -74,13 +189,13 @@ fn render_schedule(frame: &mut Frame, app: &mut App, area: Rect) {
     // We can render the header in outer_block.
     frame.render_widget(outer_block, area);
 
-    // Iterate through all elements in the `mode_list` and stylize them.
-    let items: Vec<ListItem> = app
-        .schedule_list
-        .items
+    // Only lines matching the search are shown, and the selection is tracked in terms of the
+    // shown lines
+    let visible = app.visible_lines();
+    let items: Vec<ListItem> = visible
         .iter()
         .enumerate()
-        .map(|(i, schedule_item)| schedule_item.to_list_item(i))
+        .map(|(i, &line)| app.schedule_list.items[line].to_list_item(i))
         .collect();
 
     // Create a List from all list items and highlight the currently selected one
@@ -96,7 +211,145 @@ fn render_schedule(frame: &mut Frame, app: &mut App, area: Rect) {
         .highlight_spacing(HighlightSpacing::Always);
 
     // We can now render the item list
-    frame.render_stateful_widget(items, inner_area, &mut app.schedule_list.state);
+    if app.search.is_some() {
+        let selected = app
+            .schedule_list
+            .state
+            .selected()
+            .and_then(|i| visible.iter().position(|&x| x == i));
+        app.search_state.select(selected);
+        frame.render_stateful_widget(items, inner_area, &mut app.search_state);
+    } else {
+        frame.render_stateful_widget(items, inner_area, &mut app.schedule_list.state);
+    }
+}
+
+fn render_timeline(frame: &mut Frame, app: &App, area: Rect) {
+    let start = app.timeline_start;
+    let end = start + Duration::days(app.timeline_days);
+
+    let outer_block = Block::default()
+        .borders(Borders::NONE)
+        .fg(TEXT_COLOR)
+        .bg(SELECTION_HEADER_BG)
+        .title(format!(
+            "Timeline {} to {} (UTC)",
+            start.format("%Y%m%d %H:%M"),
+            end.format("%Y%m%d %H:%M")
+        ))
+        .title_alignment(Alignment::Center);
+    let inner_area = outer_block.inner(area);
+    frame.render_widget(outer_block, area);
+
+    let width = (inner_area.width as usize).saturating_sub(LANE_LABEL_WIDTH);
+    if width == 0 {
+        return;
+    }
+
+    // Each column covers an equal slice of the window, and is sampled at its midpoint
+    let column_span = (end - start) / width as i32;
+    let column_start = |c: usize| start + column_span * c as i32;
+    let column_times: Vec<DateTime<Utc>> = (0..width)
+        .map(|c| column_start(c) + column_span / 2)
+        .collect();
+
+    // Only lines that overlap the window get a lane, highest priority at the top
+    let visible: Vec<&ScheduleLine> = app
+        .schedule_list
+        .items
+        .iter()
+        .filter(|line| line.timestamp < end && line.end_time().is_none_or(|e| e > start))
+        .collect();
+    let mut priorities: Vec<u8> = visible.iter().map(|line| line.priority).collect();
+    priorities.sort();
+    priorities.dedup();
+    priorities.reverse();
+
+    let running: Vec<Option<&ScheduleLine>> = column_times
+        .iter()
+        .map(|t| active_at(&app.schedule_list.items, *t))
+        .collect();
+
+    // The axis marks the start of each day with its date
+    let mut axis = vec![' '; width];
+    for c in 0..width {
+        if c == 0 || column_start(c).date_naive() != column_start(c - 1).date_naive() {
+            let label = format!("|{}", column_start(c).format("%m-%d"));
+            for (offset, ch) in label.chars().enumerate() {
+                if c + offset < width {
+                    axis[c + offset] = ch;
+                }
+            }
+        }
+    }
+    let mut lines = vec![Line::from(vec![
+        Span::raw(" ".repeat(LANE_LABEL_WIDTH)),
+        Span::styled(axis.into_iter().collect::<String>(), HINT_COLOR),
+    ])];
+
+    for priority in priorities {
+        let cells: Vec<TimelineCell> = column_times
+            .iter()
+            .zip(running.iter())
+            .map(|(t, active)| match active {
+                Some(line) if line.priority == priority => TimelineCell::Running(line),
+                _ => visible
+                    .iter()
+                    .filter(|line| line.priority == priority && covers(line, *t))
+                    .max_by_key(|line| line.timestamp)
+                    .map_or(TimelineCell::Empty, |line| TimelineCell::Preempted(line)),
+            })
+            .collect();
+
+        let mut spans = vec![Span::styled(
+            format!("p{priority:<width$}", width = LANE_LABEL_WIDTH - 1),
+            KEY_COLOR,
+        )];
+        for run in cells.chunk_by(|a, b| a == b) {
+            let (line, fill, style) = match run[0] {
+                TimelineCell::Empty => {
+                    spans.push(Span::raw(" ".repeat(run.len())));
+                    continue;
+                }
+                TimelineCell::Running(line) => (
+                    line,
+                    ' ',
+                    Style::default().bg(RUNNING_COLOR).fg(Color::Black),
+                ),
+                TimelineCell::Preempted(line) => (line, '░', Style::default().fg(PREEMPTED_COLOR)),
+            };
+            // Label the bar with its experiment if there is room for it
+            let mut text = String::new();
+            if run.len() > line.experiment.len() + 1 {
+                text.push(fill);
+                text.push_str(&line.experiment);
+            }
+            while text.chars().count() < run.len() {
+                text.push(fill);
+            }
+            spans.push(Span::styled(text, style));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    if lines.len() == 1 {
+        lines.push(Line::styled(
+            "No schedule lines in this window",
+            Style::default().fg(Color::LightRed),
+        ));
+    }
+    lines.push(Line::raw(""));
+    lines.push(Line::from(vec![
+        Span::raw(" ".repeat(LANE_LABEL_WIDTH)),
+        Span::styled("  ", Style::default().bg(RUNNING_COLOR)),
+        Span::styled(" running  ", HINT_COLOR),
+        Span::styled("░░", Style::default().fg(PREEMPTED_COLOR)),
+        Span::styled(" preempted by a higher priority or later line", HINT_COLOR),
+    ]));
+
+    let timeline =
+        Paragraph::new(lines).block(Block::default().borders(Borders::NONE).bg(NORMAL_ROW_COLOR));
+    frame.render_widget(timeline, inner_area);
 }
 
 fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
@@ -104,13 +357,35 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
         // The first half of the text
         match app.current_screen {
             CurrentScreen::Main => Span::styled("Normal Mode", Style::default().fg(Color::Green)),
+            CurrentScreen::Adding | CurrentScreen::Selecting if app.editing_line.is_some() => {
+                Span::styled("Editing Mode", Style::default().fg(Color::Yellow))
+            }
             CurrentScreen::Adding | CurrentScreen::Selecting => {
                 Span::styled("Adding Mode", Style::default().fg(Color::Yellow))
             }
+            CurrentScreen::Editing => {
+                Span::styled("Editing Mode", Style::default().fg(Color::Yellow))
+            }
             CurrentScreen::Removing => {
                 Span::styled("Removing Mode", Style::default().fg(Color::Yellow))
             }
-            CurrentScreen::Exiting => Span::styled("Exiting", Style::default().fg(Color::LightRed)),
+            CurrentScreen::Timeline => {
+                Span::styled("Timeline View", Style::default().fg(Color::Cyan))
+            }
+            CurrentScreen::Diagnostics => Span::styled(
+                "Reviewing Invalid Lines",
+                Style::default().fg(Color::LightRed),
+            ),
+            CurrentScreen::Backups => {
+                Span::styled("Browsing Backups", Style::default().fg(Color::Cyan))
+            }
+            CurrentScreen::Searching => Span::styled("Searching", Style::default().fg(Color::Cyan)),
+            CurrentScreen::Archiving => {
+                Span::styled("Archiving Ended Lines", Style::default().fg(Color::Cyan))
+            }
+            CurrentScreen::Exiting | CurrentScreen::Merging => {
+                Span::styled("Exiting", Style::default().fg(Color::LightRed))
+            }
         }
         .to_owned(),
         // A white divider bar to separate the two sections
@@ -120,20 +395,29 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
             let style = Style::default().fg(Color::Green);
             if let Some(editing) = &app.currently_editing {
                 match editing {
-                    CurrentlyEditing::Year => Span::styled("Editing Year", style),
-                    CurrentlyEditing::Month => Span::styled("Editing Month", style),
-                    CurrentlyEditing::Day => Span::styled("Editing Day", style),
-                    CurrentlyEditing::Hour => Span::styled("Editing Hour", style),
-                    CurrentlyEditing::Minute => Span::styled("Editing Minute", style),
+                    CurrentlyEditing::Start => Span::styled("Editing Start", style),
+                    CurrentlyEditing::Timezone => Span::styled("Editing Time Zone", style),
                     CurrentlyEditing::Duration => Span::styled("Editing Duration", style),
+                    CurrentlyEditing::End => Span::styled("Editing End", style),
                     CurrentlyEditing::Priority => Span::styled("Editing Priority", style),
                     CurrentlyEditing::Experiment => Span::styled("Selecting Experiment", style),
                     CurrentlyEditing::SchedulingMode => {
                         Span::styled("Selecting Scheduling Mode", style)
                     }
                     CurrentlyEditing::Kwargs => Span::styled("Editing Keyword Arguments", style),
+                    CurrentlyEditing::Recurrence => Span::styled("Editing Recurrence", style),
                     CurrentlyEditing::Done => Span::styled("Confirm entry", style),
                 }
+            } else if let CurrentScreen::Searching = app.current_screen {
+                Span::styled(format!("/{}", app.search_input), style)
+            } else if let Some(search) = &app.search {
+                Span::styled(
+                    format!(
+                        "Showing {} lines matching {search}",
+                        app.visible_lines().len()
+                    ),
+                    Style::default().fg(Color::Cyan),
+                )
             } else {
                 Span::styled("Not Editing Anything", Style::default().fg(Color::DarkGray))
             }
@@ -145,7 +429,7 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
 
     let current_keys_hint: Line = {
         match app.current_screen {
-            CurrentScreen::Main | CurrentScreen::Exiting => vec![
+            CurrentScreen::Main | CurrentScreen::Exiting | CurrentScreen::Merging => vec![
                 Span::styled("(q)", Style::default().fg(KEY_COLOR)),
                 Span::styled(" to quit / ", Style::default().fg(HINT_COLOR)),
                 Span::styled("(a)", Style::default().fg(KEY_COLOR)),
@@ -155,9 +439,148 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
                 ),
                 Span::styled("(r)", Style::default().fg(KEY_COLOR)),
                 Span::styled(
-                    " to remove a schedule line",
+                    " to remove a schedule line / ",
+                    Style::default().fg(HINT_COLOR),
+                ),
+                Span::styled("(e)", Style::default().fg(KEY_COLOR)),
+                Span::styled(
+                    " to edit a schedule line / ",
                     Style::default().fg(HINT_COLOR),
                 ),
+                Span::styled("(t)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to view the timeline / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("(b)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to browse backups / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("(p)", Style::default().fg(KEY_COLOR)),
+                Span::styled(
+                    " to archive ended lines / ",
+                    Style::default().fg(HINT_COLOR),
+                ),
+                Span::styled("(/)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to search", Style::default().fg(HINT_COLOR)),
+            ]
+            .into_iter()
+            .chain(app.search.as_ref().map_or(vec![], |_| {
+                vec![
+                    Span::styled(" / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("(n)", Style::default().fg(KEY_COLOR)),
+                    Span::styled("/", Style::default().fg(HINT_COLOR)),
+                    Span::styled("(N)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(
+                        " for the next/previous match / ",
+                        Style::default().fg(HINT_COLOR),
+                    ),
+                    Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to show all lines", Style::default().fg(HINT_COLOR)),
+                ]
+            }))
+            .chain(app.undo_stack.last().map_or(vec![], |operation| {
+                vec![
+                    Span::styled(" / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("(u)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(
+                        format!(" to undo {}", operation.description),
+                        Style::default().fg(HINT_COLOR),
+                    ),
+                ]
+            }))
+            .chain(app.redo_stack.last().map_or(vec![], |operation| {
+                vec![
+                    Span::styled(" / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("(Ctrl-r)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(
+                        format!(" to redo {}", operation.description),
+                        Style::default().fg(HINT_COLOR),
+                    ),
+                ]
+            }))
+            .chain(if app.diagnostics.items.is_empty() {
+                vec![]
+            } else {
+                vec![
+                    Span::styled(" / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("(d)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(
+                        format!(" to review {} invalid lines", app.diagnostics.items.len()),
+                        Style::default().fg(Color::LightRed),
+                    ),
+                ]
+            })
+            .collect::<Vec<Span>>()
+            .into(),
+            CurrentScreen::Searching => match &app.search_error {
+                Some(e) => Span::styled(e.to_string(), Style::default().fg(Color::LightRed)).into(),
+                None => vec![
+                    Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to cancel / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("Enter", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to search by ", Style::default().fg(HINT_COLOR)),
+                    Span::styled(
+                        "exp: mode: prio:low-high date:YYYYMMDD-YYYYMMDD kwarg:",
+                        Style::default().fg(KEY_COLOR),
+                    ),
+                ]
+                .into(),
+            },
+            CurrentScreen::Archiving if app.archive_preview.is_empty() => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to go back", Style::default().fg(HINT_COLOR)),
+            ]
+            .into(),
+            CurrentScreen::Archiving => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to cancel / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("(y)", Style::default().fg(KEY_COLOR)),
+                Span::styled(
+                    format!(" to archive {} lines", app.archive_preview.len()),
+                    Style::default().fg(HINT_COLOR),
+                ),
+            ]
+            .into(),
+            CurrentScreen::Backups => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to go back / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("↑↓", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to switch backup / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("PgUp", Style::default().fg(KEY_COLOR)),
+                Span::styled("/", Style::default().fg(HINT_COLOR)),
+                Span::styled("PgDn", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to scroll changes / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("Enter", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to restore", Style::default().fg(HINT_COLOR)),
+            ]
+            .into(),
+            CurrentScreen::Diagnostics if app.fix_input.is_some() => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to cancel / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("Enter", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to save the fixed line", Style::default().fg(HINT_COLOR)),
+            ]
+            .into(),
+            CurrentScreen::Diagnostics => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to go back / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("↑↓", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to switch line / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("Enter", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to fix / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("(x)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to delete", Style::default().fg(HINT_COLOR)),
+            ]
+            .into(),
+            CurrentScreen::Timeline => vec![
+                Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to go back / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("→ ←", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to move a day / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("PgUp", Style::default().fg(KEY_COLOR)),
+                Span::styled("/", Style::default().fg(HINT_COLOR)),
+                Span::styled("PgDn", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to move a page / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("+/-", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to zoom / ", Style::default().fg(HINT_COLOR)),
+                Span::styled("g", Style::default().fg(KEY_COLOR)),
+                Span::styled(" to go to today", Style::default().fg(HINT_COLOR)),
             ]
             .into(),
             CurrentScreen::Adding => vec![
@@ -178,7 +601,7 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
                 Span::styled(" to complete", Style::default().fg(KEY_COLOR)),
             ]
             .into(),
-            CurrentScreen::Removing => vec![
+            CurrentScreen::Removing | CurrentScreen::Editing => vec![
                 Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
                 Span::styled(" to cancel / ", Style::default().fg(HINT_COLOR)),
                 Span::styled("↑↓", Style::default().fg(KEY_COLOR)),
@@ -195,6 +618,32 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
                 Span::styled(" to select", Style::default().fg(HINT_COLOR)),
             ]
             .into(),
+            CurrentScreen::Selecting
+                if matches!(app.currently_editing, Some(CurrentlyEditing::Experiment)) =>
+            {
+                vec![
+                    Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
+                    Span::styled(
+                        if app.experiment_filter.is_empty() {
+                            " to cancel / "
+                        } else {
+                            " to clear filter / "
+                        },
+                        Style::default().fg(HINT_COLOR),
+                    ),
+                    Span::styled("type", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to filter / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("↑↓", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" or ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("Home", Style::default().fg(KEY_COLOR)),
+                    Span::styled("/", Style::default().fg(HINT_COLOR)),
+                    Span::styled("End", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to move / ", Style::default().fg(HINT_COLOR)),
+                    Span::styled("Enter", Style::default().fg(KEY_COLOR)),
+                    Span::styled(" to select", Style::default().fg(HINT_COLOR)),
+                ]
+                .into()
+            }
             CurrentScreen::Selecting => vec![
                 Span::styled("(ESC)", Style::default().fg(KEY_COLOR)),
                 Span::styled(" to cancel / ", Style::default().fg(HINT_COLOR)),
@@ -225,11 +674,28 @@ fn render_footer(frame: &mut Frame, app: &mut App, area: Rect) {
 
 fn render_editor(frame: &mut Frame, app: &mut App) {
     let popup_block = Block::default()
-        .title("Create a schedule entry")
+        .title(if app.editing_line.is_some() {
+            "Edit a schedule entry"
+        } else {
+            "Create a schedule entry"
+        })
         .borders(Borders::ALL)
         .style(Style::default().bg(BG_COLOR));
 
-    let area = centered_rect(40, 25, frame.area());
+    // Make room beside the editor to describe the highlighted experiment
+    let area = if matches!(app.currently_editing, Some(CurrentlyEditing::Experiment)) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(centered_rect(80, 50, frame.area()));
+        render_experiment_details(frame, app, chunks[1]);
+        chunks[0]
+    } else if !app.recurrence_preview.is_empty() {
+        // The preview lists whole schedule lines
+        centered_rect(80, 50, frame.area())
+    } else {
+        centered_rect(40, 35, frame.area())
+    };
 
     let popup_chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -251,21 +717,35 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
             Constraint::Length(1),
             Constraint::Length(1),
             Constraint::Length(1),
+            Constraint::Length(1),
         ])
         .split(popup_chunks[0]);
 
-    let mut year_block = Paragraph::new(format!("Year: {}", app.year_input.clone()));
-    let mut month_block = Paragraph::new(format!("Month: {}", app.month_input.clone()));
-    let mut day_block = Paragraph::new(format!("Day: {}", app.day_input.clone()));
-    let mut hour_block = Paragraph::new(format!("Hour: {}", app.hour_input.clone()));
-    let mut minute_block = Paragraph::new(format!("Minute: {}", app.minute_input.clone()));
+    let mut start_block = Paragraph::new(format!("Start: {}", app.start_input.clone()));
+    let mut zone_block = Paragraph::new(if app.zone_input.is_empty() {
+        "Time Zone: UTC".to_string()
+    } else {
+        format!("Time Zone: {}", app.zone_input.clone())
+    });
+    // The start converted to UTC and the site's local time, so the offset can be checked
+    let start = app.start_time();
+    let utc_block = Paragraph::new(format!(
+        "  UTC: {}",
+        start.map_or(String::new(), |x| x.format("%Y%m%d %H:%M").to_string())
+    ))
+    .style(Style::default().fg(Color::LightCyan));
+    let local_block = Paragraph::new(format!(
+        "  Local: {}",
+        start
+            .zip(app.local_zone())
+            .map_or(String::new(), |(start, zone)| zone.format(start))
+    ))
+    .style(Style::default().fg(Color::LightCyan));
     let mut duration_block = Paragraph::new(format!("Duration: {}", app.duration_input.clone()));
+    let mut end_block = Paragraph::new(format!("End: {}", app.end_input.clone()));
     let mut priority_block = Paragraph::new(format!("Priority: {}", app.priority_input.clone()));
     let mut experiment_block = if let Some(i) = app.experiment_list.state.selected() {
-        Paragraph::new(format!(
-            "Experiment: {}",
-            app.experiment_list.items[i].clone()
-        ))
+        Paragraph::new(format!("Experiment: {}", app.experiment_list.items[i].name))
     } else {
         Paragraph::new("Experiment: ")
     };
@@ -279,52 +759,62 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
     };
 
     let mut kwargs_block = Paragraph::new(format!("Kwargs: {}", app.kwarg_input.clone()));
+    let mut recurrence_block = Paragraph::new(format!("Repeat: {}", app.recurrence_input.clone()));
     let mut done_block = Paragraph::new("Enter");
 
     let active_style = Style::default().bg(Color::LightYellow).fg(Color::Black);
 
-    match app
-        .currently_editing
-        .unwrap_or_else(|| CurrentlyEditing::Year)
-    {
-        CurrentlyEditing::Year => year_block = year_block.style(active_style),
-        CurrentlyEditing::Month => month_block = month_block.style(active_style),
-        CurrentlyEditing::Day => day_block = day_block.style(active_style),
-        CurrentlyEditing::Hour => hour_block = hour_block.style(active_style),
-        CurrentlyEditing::Minute => minute_block = minute_block.style(active_style),
+    match app.currently_editing.unwrap_or(CurrentlyEditing::Start) {
+        CurrentlyEditing::Start => start_block = start_block.style(active_style),
+        CurrentlyEditing::Timezone => zone_block = zone_block.style(active_style),
         CurrentlyEditing::Duration => duration_block = duration_block.style(active_style),
+        CurrentlyEditing::End => end_block = end_block.style(active_style),
         CurrentlyEditing::Priority => priority_block = priority_block.style(active_style),
         CurrentlyEditing::Experiment => experiment_block = experiment_block.style(active_style),
         CurrentlyEditing::SchedulingMode => mode_block = mode_block.style(active_style),
         CurrentlyEditing::Kwargs => kwargs_block = kwargs_block.style(active_style),
+        CurrentlyEditing::Recurrence => recurrence_block = recurrence_block.style(active_style),
         CurrentlyEditing::Done => done_block = done_block.style(active_style),
     };
 
     frame.render_widget(popup_block, area);
 
-    frame.render_widget(year_block, line_chunks[0]);
-    frame.render_widget(month_block, line_chunks[1]);
-    frame.render_widget(day_block, line_chunks[2]);
-    frame.render_widget(hour_block, line_chunks[3]);
-    frame.render_widget(minute_block, line_chunks[4]);
-    frame.render_widget(duration_block, line_chunks[5]);
+    frame.render_widget(start_block, line_chunks[0]);
+    frame.render_widget(zone_block, line_chunks[1]);
+    frame.render_widget(utc_block, line_chunks[2]);
+    frame.render_widget(local_block, line_chunks[3]);
+    frame.render_widget(duration_block, line_chunks[4]);
+    frame.render_widget(end_block, line_chunks[5]);
     frame.render_widget(priority_block, line_chunks[6]);
     frame.render_widget(experiment_block, line_chunks[7]);
     frame.render_widget(mode_block, line_chunks[8]);
     frame.render_widget(kwargs_block, line_chunks[9]);
-    frame.render_widget(done_block, line_chunks[10]);
+    frame.render_widget(recurrence_block, line_chunks[10]);
+    frame.render_widget(done_block, line_chunks[11]);
 
     let title = match app.currently_editing {
-        Some(CurrentlyEditing::Experiment) => "Possible Experiments",
-        Some(CurrentlyEditing::SchedulingMode) => "Scheduling Modes",
+        Some(CurrentlyEditing::Experiment) if !app.experiment_filter.is_empty() => {
+            format!("Filter: {}", app.experiment_filter)
+        }
+        Some(CurrentlyEditing::Experiment) => "Possible Experiments".to_string(),
+        Some(CurrentlyEditing::SchedulingMode) => "Scheduling Modes".to_string(),
         _ => {
             if app.last_err.is_some() {
                 "Error"
+            } else if !app.recurrence_preview.is_empty() {
+                "Preview"
+            } else if !app.pending_warnings.is_empty() {
+                "Warnings"
             } else {
                 "Restrictions"
             }
         }
+        .to_string(),
     };
+    let showing_list = matches!(
+        app.currently_editing,
+        Some(CurrentlyEditing::Experiment | CurrentlyEditing::SchedulingMode)
+    );
 
     // We create two blocks, one is for the header (outer) and the other is for list (inner).
     let outer_block = Block::default()
@@ -347,11 +837,42 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
 
     let paragraph: Paragraph;
     if app.last_err.is_some() {
-        paragraph = Paragraph::new(format!("{:?}", app.last_err.clone().unwrap()))
+        paragraph = Paragraph::new(app.last_err.clone().unwrap().to_string())
             .style(Style::default().bg(Color::Indexed(196)).fg(Color::Black))
             .block(inner_block)
             .wrap(Wrap { trim: true });
         frame.render_widget(paragraph, inner_area);
+    } else if !app.recurrence_preview.is_empty() && !showing_list {
+        let mut lines = vec![];
+        for (line, warnings) in app.recurrence_preview.iter() {
+            lines.push(Line::styled(line.format(), TEXT_COLOR));
+            for warning in warnings.iter() {
+                lines.push(Line::styled(format!("  {warning}"), WARNING_COLOR));
+            }
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::styled(
+            format!(
+                "Press Enter again to add {} lines",
+                app.recurrence_preview.len()
+            ),
+            Color::LightCyan,
+        ));
+        paragraph = Paragraph::new(lines)
+            .block(inner_block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, inner_area);
+    } else if !app.pending_warnings.is_empty() && !showing_list {
+        let mut text = String::new();
+        for warning in app.pending_warnings.iter() {
+            text.push_str(&format!("{warning}\n"));
+        }
+        text.push_str("\nPress Enter again to add anyway");
+        paragraph = Paragraph::new(text)
+            .style(Style::default().bg(WARNING_COLOR).fg(Color::Black))
+            .block(inner_block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, inner_area);
     } else if let Some(editing) = app.currently_editing {
         match editing {
             CurrentlyEditing::Experiment => {
@@ -366,7 +887,34 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
                             0 => EXP_ROW_DARK,
                             _ => EXP_ROW_LIGHT,
                         };
-                        ListItem::new(Line::styled(item, TEXT_COLOR)).bg(bg_color)
+                        // Highlight the characters that matched the filter
+                        let matched = fuzzy_match(&app.experiment_filter, &item.name)
+                            .map_or(vec![], |(_, indices)| indices);
+                        let mut line = Line::from(
+                            item.name
+                                .chars()
+                                .enumerate()
+                                .map(|(i, c)| {
+                                    if matched.contains(&i) {
+                                        Span::styled(
+                                            c.to_string(),
+                                            Style::default()
+                                                .fg(KEY_COLOR)
+                                                .add_modifier(Modifier::UNDERLINED),
+                                        )
+                                    } else {
+                                        Span::styled(c.to_string(), TEXT_COLOR)
+                                    }
+                                })
+                                .collect::<Vec<_>>(),
+                        );
+                        if let Some(summary) = item.summary() {
+                            line.push_span(Span::styled(
+                                format!("  {summary}"),
+                                Style::default().fg(HINT_COLOR),
+                            ));
+                        }
+                        ListItem::new(line).bg(bg_color)
                     })
                     .collect();
                 // Create a List from all list items and highlight the currently selected one
@@ -404,17 +952,24 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
             }
             _ => {
                 let text = match editing {
-                    CurrentlyEditing::Year => "2000 <= year <= 2050",
-                    CurrentlyEditing::Month => "1 <= month <= 12",
-                    CurrentlyEditing::Day => "1 <= day <= 31",
-                    CurrentlyEditing::Hour => "0 <= hour <= 23",
-                    CurrentlyEditing::Minute => "0 <= minute <= 59",
+                    CurrentlyEditing::Start => {
+                        "e.g. now, +2h, 18:00,\ntomorrow 18:00,\n20250301 18:00 or\n2025-03-01T18:00Z"
+                    }
+                    CurrentlyEditing::Timezone => {
+                        "zone of the start and end\nUTC if left empty\nlocal or solar for this site\nor e.g. America/Regina"
+                    }
                     CurrentlyEditing::Duration => {
                         "in minutes, > 0\n1 day = 1440 min\n3 days = 4320 min\n5 days = 7200 min"
                     }
+                    CurrentlyEditing::End => {
+                        "instead of a duration, e.g.\n+90m after the start,\n20:00, tomorrow 06:00\nor 20250302 06:00"
+                    }
                     CurrentlyEditing::Priority => {
                         "0 <= priority <= 20\n0 is lowest priority\n20 is highest priority\n"
                     }
+                    CurrentlyEditing::Recurrence => {
+                        "leave empty to add once, or e.g.\nweekly count=6\nmonthly until=20251231\ndaily every=2 count=10"
+                    }
                     _ => "",
                 };
                 paragraph = Paragraph::new(text)
@@ -428,24 +983,317 @@ fn render_editor(frame: &mut Frame, app: &mut App) {
     }
 }
 
-fn render_exit_screen(frame: &mut Frame, app: &mut App) {
+/// Describes the highlighted experiment, so that similarly named experiments can be told apart.
+fn render_experiment_details(frame: &mut Frame, app: &App, area: Rect) {
+    frame.render_widget(Clear, area);
+    let block = Block::default()
+        .title("Experiment Details")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR));
+    let Some(experiment) = app
+        .experiment_list
+        .state
+        .selected()
+        .and_then(|i| app.experiment_list.items.get(i))
+    else {
+        frame.render_widget(block, area);
+        return;
+    };
+
+    let label = Style::default().fg(HINT_COLOR);
+    let mut lines = vec![Line::styled(
+        experiment.name.clone(),
+        Style::default().fg(TEXT_COLOR).add_modifier(Modifier::BOLD),
+    )];
+    let Some(path) = &experiment.path else {
+        lines.push(Line::styled(
+            "Not found in the experiments directory",
+            Style::default().fg(WARNING_COLOR),
+        ));
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+        return;
+    };
+    lines.push(Line::from(vec![
+        Span::styled("Path: ", label),
+        Span::raw(path.display().to_string()),
+    ]));
+    if let Some(modified) = experiment.modified {
+        lines.push(Line::from(vec![
+            Span::styled("Modified: ", label),
+            Span::raw(modified.format("%Y-%m-%d %H:%M UTC").to_string()),
+        ]));
+    }
+    if let Some(cpid) = experiment.cpid {
+        lines.push(Line::from(vec![
+            Span::styled("CPID: ", label),
+            Span::raw(cpid.to_string()),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        Span::styled("Slices: ", label),
+        Span::raw(experiment.slices.to_string()),
+    ]));
+
+    lines.push(Line::raw(""));
+    match &experiment.description {
+        Some(description) => lines.extend(description.lines().map(|x| Line::raw(x.to_string()))),
+        None => lines.push(Line::styled("No description", label)),
+    }
+
+    lines.push(Line::raw(""));
+    if experiment.kwargs.is_empty() {
+        lines.push(Line::styled("No known kwargs", label));
+    } else {
+        lines.push(Line::styled("Kwargs:", label));
+    }
+    for kwarg in experiment.kwargs.iter() {
+        let mut spans = vec![Span::styled(
+            format!("  {}", kwarg.name),
+            Style::default().fg(KEY_COLOR),
+        )];
+        if let Some(kind) = kwarg.kind {
+            spans.push(Span::raw(format!(" ({kind})")));
+        }
+        if let Some(description) = &kwarg.description {
+            spans.push(Span::raw(format!(": {description}")));
+        }
+        lines.push(Line::from(spans));
+    }
+    if experiment.var_kwargs {
+        lines.push(Line::styled(
+            "  Takes **kwargs, so may accept others",
+            label,
+        ));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn render_diagnostics(frame: &mut Frame, app: &mut App) {
+    let area = centered_rect(70, 60, frame.area());
+    frame.render_widget(Clear, area);
+
+    let popup_block = Block::default()
+        .title(format!(
+            "{} lines could not be read",
+            app.diagnostics.items.len()
+        ))
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR).fg(Color::LightRed));
+    let inner_area = popup_block.inner(area);
+    frame.render_widget(popup_block, area);
+
+    let fix_height = if app.fix_input.is_some() { 3 } else { 0 };
+    let error_height = if app.last_err.is_some() { 3 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(fix_height),
+            Constraint::Length(error_height),
+        ])
+        .split(inner_area);
+
+    let items: Vec<ListItem> = app
+        .diagnostics
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, diagnostic)| {
+            let bg_color = match i % 2 {
+                0 => NORMAL_ROW_COLOR,
+                _ => ALT_ROW_COLOR,
+            };
+            ListItem::new(Text::from(vec![
+                Line::styled(
+                    format!("line {}: {}", diagnostic.line_number, diagnostic.raw),
+                    TEXT_COLOR,
+                ),
+                Line::styled(format!("    {}", diagnostic.error), Color::LightRed),
+            ]))
+            .bg(bg_color)
+        })
+        .collect();
+    let items = List::new(items)
+        .highlight_style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::REVERSED)
+                .fg(SELECTION_STYLE_FG),
+        )
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+    frame.render_stateful_widget(items, chunks[0], &mut app.diagnostics.state);
+
+    if let Some(input) = &app.fix_input {
+        let fix_widget = Paragraph::new(input.clone())
+            .style(Style::default().bg(Color::LightYellow).fg(Color::Black))
+            .block(Block::default().title("Fixed line").borders(Borders::ALL));
+        frame.render_widget(fix_widget, chunks[1]);
+    }
+    if let Some(err) = &app.last_err {
+        let error_widget = Paragraph::new(err.to_string())
+            .style(Style::default().bg(Color::Indexed(196)).fg(Color::Black))
+            .block(Block::default().title("Error").borders(Borders::ALL));
+        frame.render_widget(error_widget, chunks[2]);
+    }
+}
+
+fn render_archive(frame: &mut Frame, app: &App) {
+    let area = centered_rect(60, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title("Lines that have ended")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR));
+    let text: Text = if app.archive_preview.is_empty() {
+        Line::styled("No lines have ended", TEXT_COLOR).into()
+    } else {
+        app.archive_preview
+            .iter()
+            .map(|line| Line::styled(line.format(), Color::LightRed))
+            .collect::<Vec<Line>>()
+            .into()
+    };
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn render_backups(frame: &mut Frame, app: &mut App) {
+    let area = centered_rect(80, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(26), Constraint::Min(1)])
+        .split(area);
+
+    let list_block = Block::default()
+        .title("Backups")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR));
+    if app.backups.items.is_empty() {
+        let paragraph = Paragraph::new("No backups yet")
+            .style(Style::default().fg(TEXT_COLOR))
+            .block(list_block);
+        frame.render_widget(paragraph, chunks[0]);
+    } else {
+        let items: Vec<ListItem> = app
+            .backups
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, backup)| {
+                let bg_color = match i % 2 {
+                    0 => NORMAL_ROW_COLOR,
+                    _ => ALT_ROW_COLOR,
+                };
+                ListItem::new(Line::styled(
+                    backup.time.format("%Y%m%d %H:%M:%S").to_string(),
+                    TEXT_COLOR,
+                ))
+                .bg(bg_color)
+            })
+            .collect();
+        let items = List::new(items)
+            .block(list_block)
+            .highlight_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::REVERSED)
+                    .fg(SELECTION_STYLE_FG),
+            )
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        frame.render_stateful_widget(items, chunks[0], &mut app.backups.state);
+    }
+
+    // Only the lines that would change are shown, as schedules are mostly unchanged between saves
+    let diff_block = Block::default()
+        .title("Changes restoring this backup would make")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR));
+    let text: Text = match &app.backup_changes {
+        Ok(lines) if lines.is_empty() => Line::styled("No changes", TEXT_COLOR).into(),
+        Ok(lines) => lines
+            .iter()
+            .map(|line| match line {
+                DiffLine::Removed(_) => Line::styled(line.to_string(), Color::LightRed),
+                _ => Line::styled(line.to_string(), Color::LightGreen),
+            })
+            .collect::<Vec<Line>>()
+            .into(),
+        Err(e) => Line::styled(format!("Unable to read backup: {e}"), Color::LightRed).into(),
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(diff_block)
+            .scroll((app.backup_scroll, 0)),
+        chunks[1],
+    );
+}
+
+fn render_exit_screen(frame: &mut Frame, session: &mut Session) {
     frame.render_widget(Clear, frame.area()); // this clears the entire screen and anything already drawn
 
+    // With more than one site, a summary of the changes to each site goes above the details of
+    // the site being shown
+    let summary_height = if session.apps.len() > 1 {
+        session.apps.len() as u16 + 2
+    } else {
+        3
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3),
+            Constraint::Length(summary_height),
             Constraint::Min(1),
             Constraint::Length(3),
         ])
         .split(frame.area());
+    if session.apps.len() > 1 {
+        render_site_summary(frame, session, chunks[0]);
+    }
+    let app = session.app_mut();
 
-    // The body, giving the diff in the schedule file
+    // The body, giving the diff in the schedule file and any warnings about the additions
+    let warnings = app.change_warnings();
+    let warning_height = if warnings.is_empty() {
+        0
+    } else {
+        warnings
+            .iter()
+            .map(|(_, w)| w.to_string().lines().count() as u16 + 1)
+            .sum::<u16>()
+            + 2
+    };
+    let body_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Max(warning_height)])
+        .split(chunks[1]);
     let diff_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .margin(1)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chunks[1]);
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(body_chunks[0]);
     let add_block = Block::default()
         .title("Additions")
         .borders(Borders::ALL)
@@ -460,17 +1308,47 @@ fn render_exit_screen(frame: &mut Frame, app: &mut App) {
     app.additions.sort();
     app.deletions.sort();
     for line in app.additions.iter().rev() {
-        add_text.extend(line.format().chars());
+        add_text.push_str(&line.format());
         add_text.push('\n');
     }
     for line in app.deletions.iter().rev() {
-        del_text.extend(line.format().chars());
+        del_text.push_str(&line.format());
+        if app.archived.contains(line) {
+            del_text.push_str(" (archived)");
+        }
         del_text.push('\n');
     }
+    for raw in app.removed_invalid.iter() {
+        del_text.push_str(&format!("{raw} (invalid)\n"));
+    }
+    let modify_block = Block::default()
+        .title("Modifications")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR).fg(Color::LightYellow));
+    let mut modify_text = String::new();
+    app.modifications.sort();
+    for (old, new) in app.modifications.iter().rev() {
+        modify_text.push_str(&format!("- {}\n+ {}\n", old.format(), new.format()));
+    }
     let add_widget = Paragraph::new(add_text).block(add_block);
     let del_widget = Paragraph::new(del_text).block(delete_block);
+    let modify_widget = Paragraph::new(modify_text).block(modify_block);
     frame.render_widget(add_widget, diff_chunks[0]);
     frame.render_widget(del_widget, diff_chunks[1]);
+    frame.render_widget(modify_widget, diff_chunks[2]);
+
+    if !warnings.is_empty() {
+        let warning_block = Block::default()
+            .title("Warnings")
+            .borders(Borders::ALL)
+            .style(Style::default().bg(BG_COLOR).fg(WARNING_COLOR));
+        let mut warning_text = String::new();
+        for (line, warning) in warnings.iter() {
+            warning_text.push_str(&format!("{}\n  {warning}\n", line.format()));
+        }
+        let warning_widget = Paragraph::new(warning_text).block(warning_block);
+        frame.render_widget(warning_widget, body_chunks[1]);
+    }
 
     // The footer, detailing how to proceed
     let popup_block = Block::default()
@@ -478,36 +1356,60 @@ fn render_exit_screen(frame: &mut Frame, app: &mut App) {
         .title_alignment(Alignment::Center)
         .borders(Borders::NONE)
         .style(Style::default().bg(Color::Black));
-    let exit_text: Line = vec![
-        Span::styled("Write to file ", Style::default().fg(Color::LightBlue)),
-        Span::styled(
-            "(y)",
-            Style::default()
-                .fg(Color::LightYellow)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            "  /  Cancel changes and quit ",
-            Style::default().fg(Color::LightBlue),
-        ),
-        Span::styled(
-            "(n)",
-            Style::default()
-                .fg(Color::LightYellow)
-                .add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            "  /  Go back to editing ",
-            Style::default().fg(Color::LightBlue),
-        ),
-        Span::styled(
-            "(b)",
-            Style::default()
-                .fg(Color::LightYellow)
-                .add_modifier(Modifier::BOLD),
-        ),
-    ]
-    .into();
+    let key_style = Style::default()
+        .fg(Color::LightYellow)
+        .add_modifier(Modifier::BOLD);
+    let exit_text: Line = if let CurrentScreen::Merging = app.current_screen {
+        vec![
+            Span::styled(
+                "Merge my changes and write to file ",
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::styled("(m)", key_style),
+            Span::styled(
+                "  /  Overwrite with my version ",
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::styled("(o)", key_style),
+            Span::styled(
+                "  /  Go back to editing ",
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::styled("(b)", key_style),
+        ]
+        .into()
+    } else {
+        vec![
+            Span::styled("Write to file ", Style::default().fg(Color::LightBlue)),
+            Span::styled(
+                "(y)",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                "  /  Cancel changes and quit ",
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::styled(
+                "(n)",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                "  /  Go back to editing ",
+                Style::default().fg(Color::LightBlue),
+            ),
+            Span::styled(
+                "(b)",
+                Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]
+        .into()
+    };
 
     // the `trim: false` will stop the text from being cut off when over the edge of the block
     let exit_paragraph = Paragraph::new(exit_text)
@@ -518,6 +1420,78 @@ fn render_exit_screen(frame: &mut Frame, app: &mut App) {
     frame.render_widget(exit_paragraph, chunks[2]);
 }
 
+fn render_site_summary(frame: &mut Frame, session: &Session, area: Rect) {
+    let lines: Vec<Line> = session
+        .apps
+        .iter()
+        .enumerate()
+        .map(|(i, app)| {
+            let pointer = if i == session.current { ">" } else { " " };
+            let changes = if app.has_changes() {
+                format!(
+                    "{} additions, {} deletions, {} modifications",
+                    app.additions.len(),
+                    app.deletions.len() + app.removed_invalid.len(),
+                    app.modifications.len()
+                )
+            } else {
+                "no changes".to_string()
+            };
+            let warnings = app.change_warnings().len();
+            let mut spans = vec![
+                Span::styled(format!("{pointer} {:<6}", app.site()), TEXT_COLOR),
+                Span::styled(changes, HINT_COLOR),
+            ];
+            if warnings > 0 {
+                spans.push(Span::styled(
+                    format!(", {warnings} warnings"),
+                    WARNING_COLOR,
+                ));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let block = Block::default()
+        .title("Sites (Tab to switch)")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn render_merge(frame: &mut Frame, app: &App) {
+    let Some(merge) = &app.merge else {
+        return;
+    };
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let popup_block = Block::default()
+        .title("Schedule changed on disk")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(BG_COLOR).fg(WARNING_COLOR));
+
+    let when = merge.stamp.modified.map_or(String::new(), |x| {
+        format!(" at {}", DateTime::<Utc>::from(x).format("%Y%m%d %H:%M"))
+    });
+    let mut text = format!(
+        "{} was changed by someone else{when} after it was loaded.\n\n",
+        app.scd_path.display()
+    );
+    if merge.conflicts.is_empty() {
+        text.push_str("Your changes can be merged onto the new version without conflicts.\n");
+    } else {
+        text.push_str("Your changes can be merged onto the new version, except that:\n");
+        for conflict in merge.conflicts.iter() {
+            text.push_str(&format!("\n{conflict}\n"));
+        }
+    }
+    let paragraph = Paragraph::new(text)
+        .block(popup_block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     // Cut the given rectangle into three vertical pieces